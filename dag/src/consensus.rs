@@ -0,0 +1,1565 @@
+//! Virtual-voting consensus with stake-weighted finality.
+//!
+//! Rounds run in one of two modes (see [`ConsensusMode`]). The default,
+//! `Simulated`, assumes every registered validator witnesses and approves
+//! each vertex, which lets single-node deployments finalize without network
+//! traffic. `Real` counts only locally signed votes and verified network
+//! votes.
+
+use std::collections::HashMap;
+
+use blst::min_sig::{PublicKey, SecretKey, Signature};
+use blst::BLST_ERROR;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::DAGError;
+use crate::vertex::{now_millis, DAGVertex, VertexHash};
+
+/// Domain separation tag for vote signatures (the standard min-sig RO tag).
+const VOTE_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// Performance score floor below which a validator stops counting toward
+/// quorum and is removed at the next epoch boundary.
+pub const MIN_PERFORMANCE_SCORE: f64 = 0.5;
+
+/// Score delta for participating in a round.
+const PERFORMANCE_REWARD: f64 = 0.01;
+
+/// Score delta for sitting a round out.
+const PERFORMANCE_PENALTY: f64 = -0.05;
+
+/// Canonical byte string an approving vote signs: the vertex hash followed by
+/// the round. Every supporter of a vertex in a round signs the same message,
+/// which is what makes fast aggregate verification possible.
+pub fn vote_message(vertex_hash: &VertexHash, round: u64) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(40);
+    msg.extend_from_slice(vertex_hash);
+    msg.extend_from_slice(&round.to_le_bytes());
+    msg
+}
+
+/// Whether rounds run on fabricated local votes or real network votes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsensusMode {
+    /// Every active validator is assumed to witness and approve each vertex.
+    /// Useful for tests and single-node deployments; unsafe in production.
+    Simulated,
+    /// Only locally signed votes and verified network votes count; nothing
+    /// is approved on a validator's behalf.
+    Real,
+}
+
+/// Consensus tuning knobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsensusConfig {
+    /// Simulated or real voting; see [`ConsensusMode`].
+    pub mode: ConsensusMode,
+    /// Fraction of stake required for finality. Defaults to 2/3.
+    pub bft_threshold: f64,
+    /// Nominal round duration used by automatic triggering.
+    pub round_duration_ms: u64,
+    /// Number of rounds per epoch.
+    pub epoch_length: u64,
+    /// Minimum validator count before rounds are processed.
+    pub min_validators: usize,
+    /// Finalized vertices between signed checkpoints; 0 disables them.
+    pub checkpoint_interval: u64,
+    /// How many rounds either side of the current one a network vote may
+    /// target; votes outside the window are rejected as replays.
+    pub vote_round_window: u64,
+    /// Smallest stake accepted into the validator set; under-staked
+    /// registrations are rejected and validators slashed below it are removed
+    /// at the next epoch boundary. 0 disables the floor (the documented
+    /// production value is 50k CS).
+    pub min_validator_stake: u64,
+    /// Rounds a finality proof is retained after the round that produced it;
+    /// older proofs are garbage-collected, leaving checkpoints as the durable
+    /// record. 0 keeps every proof forever.
+    pub proof_retention_rounds: u64,
+    /// Largest fraction of the active stake any single validator may
+    /// contribute to a finality tally; stake above the cap is ignored when
+    /// voting, so no one validator can finalize alone. 1.0 disables the cap.
+    pub max_vote_weight_fraction: f64,
+    /// Milliseconds a validator may go without a heartbeat (a verified vote
+    /// or a peer ping) before its stake stops counting toward quorum. A
+    /// silent validator is excluded, not removed, and resumes on its next
+    /// heartbeat. 0 disables the timeout.
+    pub validator_liveness_timeout_ms: u64,
+}
+
+impl Default for ConsensusConfig {
+    fn default() -> Self {
+        ConsensusConfig {
+            mode: ConsensusMode::Simulated,
+            bft_threshold: 0.67,
+            round_duration_ms: 5_000,
+            epoch_length: 100,
+            min_validators: 1,
+            checkpoint_interval: 100,
+            vote_round_window: 10,
+            min_validator_stake: 0,
+            proof_retention_rounds: 0,
+            max_vote_weight_fraction: 1.0,
+            validator_liveness_timeout_ms: 0,
+        }
+    }
+}
+
+/// Identifier a validator registers under.
+pub type ValidatorId = String;
+
+/// Validator tier, assigned from stake size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ValidatorTier {
+    Genesis,
+    Staked,
+    Community,
+}
+
+/// Per-validator bookkeeping.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorInfo {
+    pub validator_id: String,
+    pub stake: u64,
+    pub tier: ValidatorTier,
+    /// Rolling participation/timeliness score in `[0, 1]`.
+    pub performance_score: f64,
+    /// Last observed activity, in milliseconds since the epoch.
+    pub last_activity: u64,
+    /// BLS (min-sig) public key bytes; empty for validators without a key.
+    pub public_key: Vec<u8>,
+}
+
+impl ValidatorInfo {
+    pub fn new(validator_id: String, stake: u64, public_key: Vec<u8>) -> Self {
+        let tier = if stake >= 500_000_000_000 {
+            ValidatorTier::Genesis
+        } else if stake >= 50_000_000_000 {
+            ValidatorTier::Staked
+        } else {
+            ValidatorTier::Community
+        };
+        ValidatorInfo {
+            validator_id,
+            stake,
+            tier,
+            performance_score: 1.0,
+            last_activity: now_millis(),
+            public_key,
+        }
+    }
+
+    /// Nudges the performance score by `delta`, clamped to `[0, 1]`.
+    /// Deliberately not a heartbeat: round bookkeeping touches every
+    /// validator, present or not, and must not mask a dead one.
+    pub fn update_performance(&mut self, delta: f64) {
+        self.performance_score = (self.performance_score + delta).clamp(0.0, 1.0);
+    }
+}
+
+/// A single validator's vote on a vertex in a round.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualVote {
+    pub validator_id: String,
+    pub vertex_hash: VertexHash,
+    pub round: u64,
+    pub approve: bool,
+    pub timestamp: u64,
+    /// 48-byte BLS signature over the vote contents.
+    pub signature: Vec<u8>,
+}
+
+/// Aggregated-signature form of a vote set: one BLS aggregate plus a
+/// participant bitmap over the epoch's validator ordering.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateInfo {
+    pub aggregate_signature: Vec<u8>,
+    /// Bitmap over the sorted validator set; bit i set = validator i signed.
+    pub participants: Vec<u8>,
+    pub vote_count: u32,
+}
+
+/// Running tally of votes for one vertex.
+#[derive(Debug, Clone)]
+pub struct VoteRecord {
+    pub vertex_hash: VertexHash,
+    pub votes: HashMap<String, VirtualVote>,
+    pub approvals: u32,
+    pub rejections: u32,
+    pub voting_stake: u64,
+    pub consensus_reached: bool,
+}
+
+impl VoteRecord {
+    fn new(vertex_hash: VertexHash) -> Self {
+        VoteRecord {
+            vertex_hash,
+            votes: HashMap::new(),
+            approvals: 0,
+            rejections: 0,
+            voting_stake: 0,
+            consensus_reached: false,
+        }
+    }
+}
+
+/// Read-only view of a vertex's vote tally, for debugging why a vertex
+/// has or hasn't finalized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteSummary {
+    pub vertex_hash: VertexHash,
+    pub approvals: u32,
+    pub rejections: u32,
+    /// Stake behind the votes received so far.
+    pub voting_stake: u64,
+    /// Stake finality currently requires.
+    pub required_stake: u64,
+    pub consensus_reached: bool,
+}
+
+/// Proof that a vertex reached finality in a round.
+///
+/// Supporting votes are carried as a single BLS aggregate plus a participant
+/// bitmap rather than the full vote list; see [`AggregateInfo`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalityProof {
+    pub vertex_hash: VertexHash,
+    pub round: u64,
+    pub timestamp: u64,
+    pub aggregate: AggregateInfo,
+    pub supporting_stake: u64,
+    pub total_stake: u64,
+}
+
+/// A numbered batch of vertices finalized in one round, chained by hash.
+///
+/// The DAG itself has no blocks; this layers block semantics on top of
+/// consensus finality for explorers and exchanges that expect block numbers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochBlock {
+    /// Sequential block number, starting at 0.
+    pub number: u64,
+    /// Finalized vertices in canonical apply order.
+    pub finalized_vertices: Vec<VertexHash>,
+    /// Cumulative finalized state root after this block.
+    pub state_root: [u8; 32],
+    /// Hash of the previous epoch block; zero for the first.
+    pub prev_block_hash: [u8; 32],
+}
+
+impl EpochBlock {
+    /// Hash chaining this block to its successor.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.number.to_le_bytes());
+        for vertex in &self.finalized_vertices {
+            hasher.update(vertex);
+        }
+        hasher.update(self.state_root);
+        hasher.update(self.prev_block_hash);
+        hasher.finalize().into()
+    }
+}
+
+/// Signed summary of finalized DAG state, produced every
+/// `checkpoint_interval` finalizations so light clients can sync forward from
+/// the latest checkpoint instead of from genesis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Highest finalized logical clock at checkpoint time.
+    pub clock: u64,
+    /// Cumulative hash over every finalized vertex hash so far.
+    pub state_root: [u8; 32],
+    /// Per-shard cumulative hashes, sorted by shard id.
+    pub shard_roots: Vec<(u32, [u8; 32])>,
+    pub timestamp: u64,
+    pub aggregate: AggregateInfo,
+}
+
+/// Canonical byte string the validator set signs for a checkpoint.
+pub fn checkpoint_message(
+    clock: u64,
+    state_root: &[u8; 32],
+    shard_roots: &[(u32, [u8; 32])],
+) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(40 + shard_roots.len() * 36);
+    msg.extend_from_slice(&clock.to_le_bytes());
+    msg.extend_from_slice(state_root);
+    for (shard_id, root) in shard_roots {
+        msg.extend_from_slice(&shard_id.to_le_bytes());
+        msg.extend_from_slice(root);
+    }
+    msg
+}
+
+/// Commands accepted by the consensus driver.
+#[derive(Debug, Clone)]
+pub enum ConsensusCommand {
+    /// Process a consensus round now.
+    TriggerRound,
+    /// Re-evaluate peer count before triggering rounds.
+    CheckPeers,
+}
+
+/// The virtual-voting consensus coordinator.
+pub struct VirtualVotingConsensus {
+    pub config: ConsensusConfig,
+    validators: HashMap<String, ValidatorInfo>,
+    current_round: u64,
+    current_epoch: u64,
+    vote_records: HashMap<VertexHash, VoteRecord>,
+    finality_proofs: HashMap<VertexHash, FinalityProof>,
+    /// Round each vertex finalized in; unlike `finality_proofs` this survives
+    /// proof garbage collection, keeping finality queries exact.
+    finalized_rounds: HashMap<VertexHash, u64>,
+    /// Signing keys this process holds; in simulated rounds every validator
+    /// with a key here casts a real signature.
+    signing_keys: HashMap<String, SecretKey>,
+    /// Canonical apply order of each round's finalized vertices.
+    finalized_by_round: HashMap<u64, Vec<VertexHash>>,
+    /// Epoch blocks produced so far, indexed by block number.
+    blocks: Vec<EpochBlock>,
+    finalized_since_checkpoint: u64,
+    state_root: [u8; 32],
+    shard_roots: HashMap<u32, [u8; 32]>,
+    max_finalized_clock: u64,
+    latest_checkpoint: Option<Checkpoint>,
+}
+
+impl VirtualVotingConsensus {
+    pub fn new(mut config: ConsensusConfig) -> Self {
+        // BFT safety needs strictly more than 2/3; silently weaker
+        // thresholds would be a misconfiguration, so fall back loudly.
+        if !(config.bft_threshold > 2.0 / 3.0 && config.bft_threshold <= 1.0) {
+            log::warn!(
+                "bft_threshold {} outside (2/3, 1], using default",
+                config.bft_threshold
+            );
+            config.bft_threshold = ConsensusConfig::default().bft_threshold;
+        }
+        if !(config.max_vote_weight_fraction > 0.0 && config.max_vote_weight_fraction <= 1.0) {
+            log::warn!(
+                "max_vote_weight_fraction {} outside (0, 1], disabling the cap",
+                config.max_vote_weight_fraction
+            );
+            config.max_vote_weight_fraction = 1.0;
+        }
+        match config.mode {
+            ConsensusMode::Simulated => log::warn!(
+                "consensus mode: SIMULATED - votes are fabricated locally, do not use in production"
+            ),
+            ConsensusMode::Real => log::info!("consensus mode: real network voting"),
+        }
+        VirtualVotingConsensus {
+            config,
+            validators: HashMap::new(),
+            current_round: 0,
+            current_epoch: 0,
+            vote_records: HashMap::new(),
+            finality_proofs: HashMap::new(),
+            finalized_rounds: HashMap::new(),
+            signing_keys: HashMap::new(),
+            finalized_by_round: HashMap::new(),
+            blocks: Vec::new(),
+            finalized_since_checkpoint: 0,
+            state_root: [0u8; 32],
+            shard_roots: HashMap::new(),
+            max_finalized_clock: 0,
+            latest_checkpoint: None,
+        }
+    }
+
+    pub fn current_round(&self) -> u64 {
+        self.current_round
+    }
+
+    pub fn current_epoch(&self) -> u64 {
+        self.current_epoch
+    }
+
+    pub fn validator_count(&self) -> usize {
+        self.validators.len()
+    }
+
+    /// Registers a validator. Re-registering updates stake and key. Fails if
+    /// the stake is below [`ConsensusConfig::min_validator_stake`].
+    pub fn add_validator(&mut self, info: ValidatorInfo) -> Result<(), DAGError> {
+        if info.stake < self.config.min_validator_stake {
+            return Err(DAGError::ConsensusError(format!(
+                "validator {} stake {} below minimum {}",
+                info.validator_id, info.stake, self.config.min_validator_stake
+            )));
+        }
+        self.validators.insert(info.validator_id.clone(), info);
+        Ok(())
+    }
+
+    /// Registers a validator along with its BLS signing key. The public key
+    /// on the info is replaced with the one derived from `key`.
+    pub fn add_validator_with_key(
+        &mut self,
+        mut info: ValidatorInfo,
+        key: SecretKey,
+    ) -> Result<(), DAGError> {
+        if info.stake < self.config.min_validator_stake {
+            return Err(DAGError::ConsensusError(format!(
+                "validator {} stake {} below minimum {}",
+                info.validator_id, info.stake, self.config.min_validator_stake
+            )));
+        }
+        info.public_key = key.sk_to_pk().to_bytes().to_vec();
+        self.signing_keys.insert(info.validator_id.clone(), key);
+        self.validators.insert(info.validator_id.clone(), info);
+        Ok(())
+    }
+
+    pub fn remove_validator(&mut self, validator_id: &str) -> Option<ValidatorInfo> {
+        self.validators.remove(validator_id)
+    }
+
+    pub fn get_validator(&self, validator_id: &str) -> Option<&ValidatorInfo> {
+        self.validators.get(validator_id)
+    }
+
+    /// Marks a validator as recently alive, restoring its stake to quorum
+    /// math if a liveness timeout had excluded it. Unknown ids are ignored.
+    pub fn record_heartbeat(&mut self, validator_id: &str) {
+        if let Some(validator) = self.validators.get_mut(validator_id) {
+            validator.last_activity = now_millis();
+        }
+    }
+
+    pub fn validators(&self) -> impl Iterator<Item = &ValidatorInfo> {
+        self.validators.values()
+    }
+
+    /// Snapshot of the validator set, sorted by id.
+    pub fn list_validators(&self) -> Vec<(ValidatorId, ValidatorInfo)> {
+        let mut list: Vec<_> = self
+            .validators
+            .iter()
+            .map(|(id, info)| (id.clone(), info.clone()))
+            .collect();
+        list.sort_by(|a, b| a.0.cmp(&b.0));
+        list
+    }
+
+    /// Validator count and combined stake per tier.
+    pub fn stake_distribution(&self) -> HashMap<ValidatorTier, (usize, u64)> {
+        let mut distribution: HashMap<ValidatorTier, (usize, u64)> = HashMap::new();
+        for validator in self.validators.values() {
+            let entry = distribution.entry(validator.tier).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += validator.stake;
+        }
+        distribution
+    }
+
+    pub fn total_stake(&self) -> u64 {
+        self.validators.values().map(|v| v.stake).sum()
+    }
+
+    /// Whether a validator currently counts toward quorum: it must both be
+    /// scoring above [`MIN_PERFORMANCE_SCORE`] and, when a liveness timeout
+    /// is configured, have heartbeat within it.
+    fn is_active(&self, validator: &ValidatorInfo) -> bool {
+        if validator.performance_score < MIN_PERFORMANCE_SCORE {
+            return false;
+        }
+        let timeout = self.config.validator_liveness_timeout_ms;
+        timeout == 0 || now_millis().saturating_sub(validator.last_activity) <= timeout
+    }
+
+    /// Combined stake of validators counting toward quorum.
+    /// Active stake as counted for finality: each validator's stake after
+    /// the `max_vote_weight_fraction` cap.
+    pub fn active_stake(&self) -> u64 {
+        let cap = self.vote_weight_cap();
+        self.validators
+            .values()
+            .filter(|v| self.is_active(v))
+            .map(|v| cap.map_or(v.stake, |cap| v.stake.min(cap)))
+            .sum()
+    }
+
+    /// Per-validator stake ceiling implied by `max_vote_weight_fraction`,
+    /// or `None` when the cap is disabled.
+    fn vote_weight_cap(&self) -> Option<u64> {
+        if self.config.max_vote_weight_fraction >= 1.0 {
+            return None;
+        }
+        let raw: u64 = self
+            .validators
+            .values()
+            .filter(|v| self.is_active(v))
+            .map(|v| v.stake)
+            .sum();
+        Some((raw as f64 * self.config.max_vote_weight_fraction).floor() as u64)
+    }
+
+    /// A validator's stake as it counts towards finality.
+    fn capped_stake(&self, stake: u64) -> u64 {
+        self.vote_weight_cap().map_or(stake, |cap| stake.min(cap))
+    }
+
+    /// Stake required for finality: strictly more than `bft_threshold` of
+    /// the active stake. Underperforming validators are not expected to
+    /// vote.
+    pub fn required_stake(&self) -> u64 {
+        (self.active_stake() as f64 * self.config.bft_threshold).floor() as u64 + 1
+    }
+
+    /// Vote count required for finality: at least `bft_threshold` of the
+    /// active validator set, rounded up.
+    pub fn required_votes(&self) -> usize {
+        let n = self
+            .validators
+            .values()
+            .filter(|v| self.is_active(v))
+            .count();
+        (n as f64 * self.config.bft_threshold).ceil() as usize
+    }
+
+    /// Whether a validator would approve this vertex.
+    ///
+    /// Simulated mode answers true for everyone (real validation happens at
+    /// insert time on the node that owns the vertex). Real mode never
+    /// approves on another validator's behalf.
+    pub fn validate_vertex_by_validator(&self, _vertex: &DAGVertex, _validator_id: &str) -> bool {
+        self.config.mode == ConsensusMode::Simulated
+    }
+
+    /// Whether a validator has witnessed the vote of another.
+    ///
+    /// Simulated mode answers true; real witness tracking is not wired yet,
+    /// so Real mode refuses rather than silently confirming.
+    pub fn validator_witnesses_vote(&self, _witness: &str, _voter: &str) -> bool {
+        self.config.mode == ConsensusMode::Simulated
+    }
+
+    /// Signs a vote on `vertex_hash` with the named validator's locally held
+    /// key. The vote targets `current_round + 1`, the round that will tally
+    /// votes cast between rounds. Rejections are signed too: the message
+    /// covers only the hash and round, and only approvals are aggregated.
+    pub fn sign_vote(
+        &self,
+        validator_id: &str,
+        vertex_hash: VertexHash,
+        approve: bool,
+    ) -> Result<VirtualVote, DAGError> {
+        let key = self.signing_keys.get(validator_id).ok_or_else(|| {
+            DAGError::ConsensusError(format!("no signing key for {validator_id}"))
+        })?;
+        let round = self.current_round + 1;
+        Ok(VirtualVote {
+            validator_id: validator_id.to_string(),
+            vertex_hash,
+            round,
+            approve,
+            timestamp: now_millis(),
+            signature: key
+                .sign(&vote_message(&vertex_hash, round), VOTE_DST, &[])
+                .to_bytes()
+                .to_vec(),
+        })
+    }
+
+    /// Ingests a vote received from the network. In [`ConsensusMode::Real`]
+    /// the vote signature must verify against the validator's registered key.
+    pub fn submit_vote(&mut self, vote: VirtualVote) -> Result<(), DAGError> {
+        // Replay protection: a vote may only target a round near the current
+        // one, and a validator's vote on a vertex counts once.
+        let window = self.config.vote_round_window;
+        if vote.round + window < self.current_round {
+            return Err(DAGError::ConsensusError(format!(
+                "stale vote for round {} at round {}",
+                vote.round, self.current_round
+            )));
+        }
+        if vote.round > self.current_round + window {
+            return Err(DAGError::ConsensusError(format!(
+                "vote for future round {} at round {}",
+                vote.round, self.current_round
+            )));
+        }
+        if self.finalized_rounds.contains_key(&vote.vertex_hash) {
+            // The vertex is already final; a late or replayed vote is moot.
+            return Ok(());
+        }
+        let validator = self.validators.get(&vote.validator_id).ok_or_else(|| {
+            DAGError::ConsensusError(format!("unknown validator {}", vote.validator_id))
+        })?;
+        let capped = self.capped_stake(validator.stake);
+        if self.config.mode == ConsensusMode::Real {
+            let public_key = PublicKey::from_bytes(&validator.public_key).map_err(|_| {
+                DAGError::ConsensusError(format!(
+                    "validator {} has no usable public key",
+                    vote.validator_id
+                ))
+            })?;
+            let signature = Signature::from_bytes(&vote.signature).map_err(|_| {
+                DAGError::ConsensusError(format!(
+                    "malformed vote signature from {}",
+                    vote.validator_id
+                ))
+            })?;
+            let message = vote_message(&vote.vertex_hash, vote.round);
+            if signature.verify(true, &message, VOTE_DST, &[], &public_key, true)
+                != BLST_ERROR::BLST_SUCCESS
+            {
+                return Err(DAGError::ConsensusError(format!(
+                    "invalid vote signature from {}",
+                    vote.validator_id
+                )));
+            }
+        }
+        // A verified vote doubles as a liveness heartbeat.
+        self.record_heartbeat(&vote.validator_id);
+        let record = self
+            .vote_records
+            .entry(vote.vertex_hash)
+            .or_insert_with(|| VoteRecord::new(vote.vertex_hash));
+        if record.votes.contains_key(&vote.validator_id) {
+            // Already counted; replaying the vote must not inflate the tally.
+            return Ok(());
+        }
+        if vote.approve {
+            record.approvals += 1;
+            record.voting_stake += capped;
+        } else {
+            record.rejections += 1;
+        }
+        record.votes.insert(vote.validator_id.clone(), vote);
+        Ok(())
+    }
+
+    /// Runs one consensus round over the given candidate vertices, returning
+    /// finality proofs for every vertex that reached the stake threshold.
+    pub fn process_consensus_round(&mut self, vertices: &[DAGVertex]) -> Vec<FinalityProof> {
+        if self.validators.len() < self.config.min_validators {
+            return Vec::new();
+        }
+        self.current_round += 1;
+        if self.config.epoch_length > 0 && self.current_round.is_multiple_of(self.config.epoch_length)
+        {
+            self.current_epoch += 1;
+            self.prune_underperforming_validators();
+        }
+
+        // A validator participates in this round if it signs locally or has
+        // already submitted a vote on one of the candidates.
+        let mut participated: std::collections::HashSet<String> =
+            self.signing_keys.keys().cloned().collect();
+        for vertex in vertices {
+            if let Some(record) = self.vote_records.get(&vertex.tx_hash) {
+                participated.extend(record.votes.keys().cloned());
+            }
+        }
+
+        let total_stake = self.active_stake();
+        let required = self.required_stake();
+        let mut proofs = Vec::new();
+        let mut finalized: Vec<&DAGVertex> = Vec::new();
+
+        for vertex in vertices {
+            if self.finalized_rounds.contains_key(&vertex.tx_hash) {
+                continue;
+            }
+            let mut record = self
+                .vote_records
+                .remove(&vertex.tx_hash)
+                .unwrap_or_else(|| VoteRecord::new(vertex.tx_hash));
+
+            let validator_ids: Vec<String> = self
+                .validators
+                .values()
+                .filter(|v| self.is_active(v))
+                .map(|v| v.validator_id.clone())
+                .collect();
+            for validator_id in validator_ids {
+                if record.votes.contains_key(&validator_id) {
+                    continue;
+                }
+                // Real mode casts no vote on a remote validator's behalf;
+                // their votes must arrive through `submit_vote`. Local keys
+                // still vote, with genuine signatures.
+                if self.config.mode == ConsensusMode::Real
+                    && !self.signing_keys.contains_key(&validator_id)
+                {
+                    continue;
+                }
+                let approve = self.config.mode == ConsensusMode::Real
+                    || self.validate_vertex_by_validator(vertex, &validator_id);
+                let signature = match self.signing_keys.get(&validator_id) {
+                    Some(key) if approve => key
+                        .sign(
+                            &vote_message(&vertex.tx_hash, self.current_round),
+                            VOTE_DST,
+                            &[],
+                        )
+                        .to_bytes()
+                        .to_vec(),
+                    _ => vec![0u8; 48],
+                };
+                let vote = VirtualVote {
+                    validator_id: validator_id.clone(),
+                    vertex_hash: vertex.tx_hash,
+                    round: self.current_round,
+                    approve,
+                    timestamp: now_millis(),
+                    signature,
+                };
+                let stake = self.capped_stake(self.validators[&validator_id].stake);
+                if approve {
+                    record.approvals += 1;
+                    record.voting_stake += stake;
+                } else {
+                    record.rejections += 1;
+                }
+                record.votes.insert(validator_id, vote);
+            }
+
+            if record.voting_stake >= required {
+                record.consensus_reached = true;
+                let proof = FinalityProof {
+                    vertex_hash: vertex.tx_hash,
+                    round: self.current_round,
+                    timestamp: now_millis(),
+                    supporting_stake: record.voting_stake,
+                    total_stake,
+                    aggregate: self.aggregate_supporting_votes(&record),
+                };
+                self.finality_proofs.insert(vertex.tx_hash, proof.clone());
+                self.finalized_rounds.insert(vertex.tx_hash, self.current_round);
+                proofs.push(proof);
+                finalized.push(vertex);
+            }
+            self.vote_records.insert(vertex.tx_hash, record);
+        }
+
+        // Canonical apply order: logical clock, then hash. Every node commits
+        // a round's vertices to state in this order, whatever order it saw
+        // them in, so concurrent transfers cannot diverge across nodes.
+        finalized.sort_by(|a, b| {
+            a.logical_clock
+                .cmp(&b.logical_clock)
+                .then_with(|| a.tx_hash.cmp(&b.tx_hash))
+        });
+        for vertex in &finalized {
+            self.note_finalized(vertex);
+        }
+        let order: Vec<VertexHash> = finalized.iter().map(|v| v.tx_hash).collect();
+        proofs.sort_by_key(|p| order.iter().position(|h| *h == p.vertex_hash));
+        if !order.is_empty() {
+            let prev_block_hash = self.blocks.last().map(EpochBlock::hash).unwrap_or([0u8; 32]);
+            self.blocks.push(EpochBlock {
+                number: self.blocks.len() as u64,
+                finalized_vertices: order.clone(),
+                state_root: self.state_root,
+                prev_block_hash,
+            });
+            self.finalized_by_round.insert(self.current_round, order);
+        }
+
+        if !vertices.is_empty() {
+            let ids: Vec<String> = self.validators.keys().cloned().collect();
+            for id in ids {
+                let delta = if participated.contains(&id) {
+                    PERFORMANCE_REWARD
+                } else {
+                    PERFORMANCE_PENALTY
+                };
+                if let Some(validator) = self.validators.get_mut(&id) {
+                    validator.update_performance(delta);
+                    if participated.contains(&id) {
+                        validator.last_activity = now_millis();
+                    }
+                }
+            }
+        }
+
+        if self.config.checkpoint_interval > 0
+            && self.finalized_since_checkpoint >= self.config.checkpoint_interval
+        {
+            self.produce_checkpoint();
+        }
+        self.gc_finality_proofs();
+        proofs
+    }
+
+    /// Drops proofs older than the configured retention window. Finality
+    /// membership is unaffected; only the heavyweight aggregate signatures
+    /// go, since checkpoints supersede them as the durable record.
+    fn gc_finality_proofs(&mut self) {
+        let retention = self.config.proof_retention_rounds;
+        if retention == 0 {
+            return;
+        }
+        // Keep exactly the last `retention` rounds' worth of proofs.
+        let cutoff = self.current_round.saturating_sub(retention);
+        self.finality_proofs.retain(|_, proof| proof.round > cutoff);
+    }
+
+    /// Drops validators whose score fell below [`MIN_PERFORMANCE_SCORE`] or
+    /// whose stake slipped under the configured floor; called at epoch
+    /// boundaries. Liveness timeouts do not remove anyone: a silent
+    /// validator is merely excluded until it heartbeats again.
+    fn prune_underperforming_validators(&mut self) {
+        let min_stake = self.config.min_validator_stake;
+        let dropped: Vec<String> = self
+            .validators
+            .values()
+            .filter(|v| v.performance_score < MIN_PERFORMANCE_SCORE || v.stake < min_stake)
+            .map(|v| v.validator_id.clone())
+            .collect();
+        for validator_id in dropped {
+            log::warn!("removing underperforming validator {validator_id}");
+            self.validators.remove(&validator_id);
+            self.signing_keys.remove(&validator_id);
+        }
+    }
+
+    /// Folds a newly finalized vertex into the running checkpoint state.
+    fn note_finalized(&mut self, vertex: &DAGVertex) {
+        let mut hasher = Sha256::new();
+        hasher.update(self.state_root);
+        hasher.update(vertex.tx_hash);
+        self.state_root = hasher.finalize().into();
+
+        let shard_root = self.shard_roots.entry(vertex.shard_id).or_insert([0u8; 32]);
+        let mut hasher = Sha256::new();
+        hasher.update(*shard_root);
+        hasher.update(vertex.tx_hash);
+        *shard_root = hasher.finalize().into();
+
+        self.max_finalized_clock = self.max_finalized_clock.max(vertex.logical_clock);
+        self.finalized_since_checkpoint += 1;
+    }
+
+    /// Signs a checkpoint over the current finalized state with every key
+    /// this process holds and records it as the latest.
+    fn produce_checkpoint(&mut self) {
+        let mut shard_roots: Vec<(u32, [u8; 32])> =
+            self.shard_roots.iter().map(|(k, v)| (*k, *v)).collect();
+        shard_roots.sort_by_key(|(shard_id, _)| *shard_id);
+        let message = checkpoint_message(self.max_finalized_clock, &self.state_root, &shard_roots);
+        let checkpoint = Checkpoint {
+            clock: self.max_finalized_clock,
+            state_root: self.state_root,
+            shard_roots,
+            timestamp: now_millis(),
+            aggregate: self.sign_and_aggregate(&message),
+        };
+        self.finalized_since_checkpoint = 0;
+        self.latest_checkpoint = Some(checkpoint);
+    }
+
+    pub fn latest_checkpoint(&self) -> Option<&Checkpoint> {
+        self.latest_checkpoint.as_ref()
+    }
+
+    /// Epoch block by number.
+    pub fn get_block(&self, number: u64) -> Option<&EpochBlock> {
+        self.blocks.get(number as usize)
+    }
+
+    /// Most recently produced epoch block.
+    pub fn latest_block(&self) -> Option<&EpochBlock> {
+        self.blocks.last()
+    }
+
+    /// Verifies a checkpoint's aggregate signature against the current
+    /// validator set.
+    pub fn verify_checkpoint(&self, checkpoint: &Checkpoint) -> bool {
+        let message = checkpoint_message(
+            checkpoint.clock,
+            &checkpoint.state_root,
+            &checkpoint.shard_roots,
+        );
+        self.verify_aggregate(&checkpoint.aggregate, &message)
+    }
+
+    /// Validator ids in the canonical (lexicographic) order the participant
+    /// bitmap is defined over.
+    pub fn sorted_validator_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.validators.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Collapses a record's approving votes into one aggregate signature plus
+    /// a participant bitmap over the sorted validator set.
+    ///
+    /// Votes carrying placeholder (all-zero) signatures still count towards
+    /// `vote_count` and the bitmap, but if any supporter's signature fails to
+    /// parse the aggregate signature is left empty and the proof will not
+    /// verify — a partial aggregate would be misleading.
+    fn aggregate_supporting_votes(&self, record: &VoteRecord) -> AggregateInfo {
+        let order = self.sorted_validator_ids();
+        let mut participants = vec![0u8; order.len().div_ceil(8)];
+        let mut signatures = Vec::new();
+        let mut vote_count = 0u32;
+        let mut all_parsed = true;
+        for (i, validator_id) in order.iter().enumerate() {
+            let Some(vote) = record.votes.get(validator_id) else {
+                continue;
+            };
+            if !vote.approve {
+                continue;
+            }
+            vote_count += 1;
+            participants[i / 8] |= 1 << (i % 8);
+            match Signature::from_bytes(&vote.signature) {
+                Ok(sig) => signatures.push(sig),
+                Err(_) => all_parsed = false,
+            }
+        }
+        let aggregate_signature = if all_parsed && !signatures.is_empty() {
+            let refs: Vec<&Signature> = signatures.iter().collect();
+            match blst::min_sig::AggregateSignature::aggregate(&refs, true) {
+                Ok(agg) => agg.to_signature().to_bytes().to_vec(),
+                Err(_) => Vec::new(),
+            }
+        } else {
+            Vec::new()
+        };
+        AggregateInfo {
+            aggregate_signature,
+            participants,
+            vote_count,
+        }
+    }
+
+    /// Signs `message` with every key this process holds and aggregates the
+    /// results into one signature plus a participant bitmap.
+    fn sign_and_aggregate(&self, message: &[u8]) -> AggregateInfo {
+        let order = self.sorted_validator_ids();
+        let mut participants = vec![0u8; order.len().div_ceil(8)];
+        let mut signatures = Vec::new();
+        let mut vote_count = 0u32;
+        for (i, validator_id) in order.iter().enumerate() {
+            if let Some(key) = self.signing_keys.get(validator_id) {
+                signatures.push(key.sign(message, VOTE_DST, &[]));
+                participants[i / 8] |= 1 << (i % 8);
+                vote_count += 1;
+            }
+        }
+        let refs: Vec<&Signature> = signatures.iter().collect();
+        let aggregate_signature = match blst::min_sig::AggregateSignature::aggregate(&refs, true) {
+            Ok(agg) => agg.to_signature().to_bytes().to_vec(),
+            Err(_) => Vec::new(),
+        };
+        AggregateInfo {
+            aggregate_signature,
+            participants,
+            vote_count,
+        }
+    }
+
+    /// Verifies an aggregate against the current validator set: the aggregate
+    /// public key is recomputed from the participant bitmap and checked
+    /// against the aggregate signature over `message`.
+    fn verify_aggregate(&self, aggregate: &AggregateInfo, message: &[u8]) -> bool {
+        let order = self.sorted_validator_ids();
+        let mut public_keys = Vec::new();
+        for (i, validator_id) in order.iter().enumerate() {
+            let set = aggregate
+                .participants
+                .get(i / 8)
+                .map(|byte| byte >> (i % 8) & 1 == 1)
+                .unwrap_or(false);
+            if !set {
+                continue;
+            }
+            match PublicKey::from_bytes(&self.validators[validator_id].public_key) {
+                Ok(pk) => public_keys.push(pk),
+                Err(_) => return false,
+            }
+        }
+        if public_keys.is_empty() || public_keys.len() as u32 != aggregate.vote_count {
+            return false;
+        }
+        let Ok(signature) = Signature::from_bytes(&aggregate.aggregate_signature) else {
+            return false;
+        };
+        let refs: Vec<&PublicKey> = public_keys.iter().collect();
+        signature.fast_aggregate_verify(true, message, VOTE_DST, &refs) == BLST_ERROR::BLST_SUCCESS
+    }
+
+    /// Verifies an aggregated finality proof against the current validator
+    /// set.
+    pub fn verify_finality_proof(&self, proof: &FinalityProof) -> bool {
+        self.verify_aggregate(
+            &proof.aggregate,
+            &vote_message(&proof.vertex_hash, proof.round),
+        )
+    }
+
+    /// The canonical apply order of the vertices finalized in `round`, empty
+    /// if the round finalized nothing (or never ran).
+    pub fn finalized_order(&self, round: u64) -> Vec<VertexHash> {
+        self.finalized_by_round
+            .get(&round)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every finalized vertex in canonical apply order: rounds ascending,
+    /// each round in its [`VirtualVotingConsensus::finalized_order`].
+    pub fn finalized_order_all(&self) -> Vec<VertexHash> {
+        let mut rounds: Vec<u64> = self.finalized_by_round.keys().copied().collect();
+        rounds.sort_unstable();
+        rounds
+            .iter()
+            .flat_map(|round| self.finalized_by_round[round].iter().copied())
+            .collect()
+    }
+
+    /// The retained proof for a finalized vertex; `None` once the retention
+    /// window has garbage-collected it (see [`ConsensusConfig::proof_retention_rounds`]).
+    pub fn get_finality_proof(&self, hash: &VertexHash) -> Option<&FinalityProof> {
+        self.finality_proofs.get(hash)
+    }
+
+    pub fn is_final(&self, hash: &VertexHash) -> bool {
+        self.finalized_rounds.contains_key(hash)
+    }
+
+    /// The round a vertex was finalized in, if it is final. Survives proof
+    /// garbage collection.
+    pub fn finalized_round(&self, hash: &VertexHash) -> Option<u64> {
+        self.finalized_rounds.get(hash).copied()
+    }
+
+    pub fn get_vote_record(&self, hash: &VertexHash) -> Option<&VoteRecord> {
+        self.vote_records.get(hash)
+    }
+
+    /// The vote tally for a vertex as a read-only [`VoteSummary`], or `None`
+    /// when no votes were recorded for it.
+    pub fn vote_summary(&self, hash: &VertexHash) -> Option<VoteSummary> {
+        let record = self.vote_records.get(hash)?;
+        Some(VoteSummary {
+            vertex_hash: record.vertex_hash,
+            approvals: record.approvals,
+            rejections: record.rejections,
+            voting_stake: record.voting_stake,
+            required_stake: self.required_stake(),
+            consensus_reached: record.consensus_reached,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::TransactionData;
+
+    fn sample_vertex(clock: u64) -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 10,
+            currency: 1,
+            nonce: clock,
+            fee: 1,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, Vec::new(), clock, 0)
+    }
+
+    fn consensus_with_validators(stakes: &[u64]) -> VirtualVotingConsensus {
+        let mut consensus = VirtualVotingConsensus::new(ConsensusConfig::default());
+        for (i, stake) in stakes.iter().enumerate() {
+            consensus.add_validator(ValidatorInfo::new(format!("v{i}"), *stake, Vec::new())).unwrap();
+        }
+        consensus
+    }
+
+    fn consensus_with_keyed_validators(stakes: &[u64]) -> VirtualVotingConsensus {
+        let mut consensus = VirtualVotingConsensus::new(ConsensusConfig::default());
+        for (i, stake) in stakes.iter().enumerate() {
+            let ikm = [i as u8 + 1; 32];
+            let key = SecretKey::key_gen(&ikm, &[]).unwrap();
+            consensus.add_validator_with_key(
+                ValidatorInfo::new(format!("v{i}"), *stake, Vec::new()),
+                key,
+            ).unwrap();
+        }
+        consensus
+    }
+
+    #[test]
+    fn proofs_outside_the_retention_window_are_garbage_collected() {
+        let mut consensus = VirtualVotingConsensus::new(ConsensusConfig {
+            proof_retention_rounds: 2,
+            ..ConsensusConfig::default()
+        });
+        consensus.add_validator(ValidatorInfo::new("v0".into(), 100, Vec::new())).unwrap();
+
+        let mut hashes = Vec::new();
+        for clock in 1..=6u64 {
+            let vertex = sample_vertex(clock);
+            let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+            assert_eq!(proofs.len(), 1);
+            hashes.push(vertex.tx_hash);
+        }
+
+        // Rounds 1..=4 fell out of the window; 5 and 6 are still retained.
+        for old in &hashes[..4] {
+            assert!(consensus.get_finality_proof(old).is_none());
+            // Finality itself outlives the proof.
+            assert!(consensus.is_final(old));
+        }
+        for recent in &hashes[4..] {
+            assert!(consensus.get_finality_proof(recent).is_some());
+        }
+    }
+
+    #[test]
+    fn validator_stake_floor_is_enforced_on_registration() {
+        let mut consensus = VirtualVotingConsensus::new(ConsensusConfig {
+            min_validator_stake: 50_000,
+            ..ConsensusConfig::default()
+        });
+
+        let under = consensus.add_validator(ValidatorInfo::new("poor".into(), 49_999, Vec::new()));
+        assert!(matches!(under, Err(DAGError::ConsensusError(_))));
+        assert_eq!(consensus.validator_count(), 0);
+
+        consensus
+            .add_validator(ValidatorInfo::new("ok".into(), 50_000, Vec::new()))
+            .unwrap();
+        assert_eq!(consensus.validator_count(), 1);
+
+        // A keyed registration is held to the same floor.
+        let key = SecretKey::key_gen(&[7u8; 32], &[]).unwrap();
+        let keyed = consensus
+            .add_validator_with_key(ValidatorInfo::new("poor2".into(), 1, Vec::new()), key);
+        assert!(keyed.is_err());
+
+        // A validator slashed below the floor is dropped at the next epoch
+        // boundary.
+        consensus.validators.get_mut("ok").unwrap().stake = 10;
+        for _ in 0..consensus.config.epoch_length {
+            consensus.process_consensus_round(&[]);
+        }
+        assert_eq!(consensus.validator_count(), 0);
+    }
+
+    #[test]
+    fn simulated_round_finalizes_vertex() {
+        let mut consensus = consensus_with_validators(&[100, 100, 100, 100]);
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert_eq!(proofs.len(), 1);
+        assert!(consensus.is_final(&vertex.tx_hash));
+        assert!(proofs[0].supporting_stake >= consensus.required_stake());
+    }
+
+    #[test]
+    fn vote_summary_reflects_a_simulated_round() {
+        let mut consensus = consensus_with_validators(&[100, 100, 100, 100]);
+        let vertex = sample_vertex(1);
+        consensus.process_consensus_round(std::slice::from_ref(&vertex));
+
+        let summary = consensus.vote_summary(&vertex.tx_hash).unwrap();
+        assert_eq!(summary.vertex_hash, vertex.tx_hash);
+        assert_eq!(summary.approvals, 4);
+        assert_eq!(summary.rejections, 0);
+        assert_eq!(summary.voting_stake, 400);
+        assert_eq!(summary.required_stake, consensus.required_stake());
+        assert!(summary.consensus_reached);
+
+        assert!(consensus.vote_summary(&[0xEE; 32]).is_none());
+    }
+
+    #[test]
+    fn finalized_rounds_produce_chained_epoch_blocks() {
+        let mut consensus = consensus_with_validators(&[100, 100, 100]);
+        let first = sample_vertex(1);
+        let second = sample_vertex(2);
+        consensus.process_consensus_round(std::slice::from_ref(&first));
+        consensus.process_consensus_round(std::slice::from_ref(&second));
+
+        let block0 = consensus.get_block(0).unwrap().clone();
+        let block1 = consensus.get_block(1).unwrap().clone();
+        assert_eq!(block0.number, 0);
+        assert_eq!(block0.finalized_vertices, vec![first.tx_hash]);
+        assert_eq!(block0.prev_block_hash, [0u8; 32]);
+        assert_eq!(block1.number, 1);
+        assert_eq!(block1.finalized_vertices, vec![second.tx_hash]);
+        assert_eq!(block1.prev_block_hash, block0.hash());
+        assert_eq!(consensus.latest_block().unwrap().number, 1);
+        assert!(consensus.get_block(2).is_none());
+    }
+
+    #[test]
+    fn real_mode_refuses_to_finalize_without_votes() {
+        let config = ConsensusConfig {
+            mode: ConsensusMode::Real,
+            ..ConsensusConfig::default()
+        };
+        let mut consensus = VirtualVotingConsensus::new(config);
+        for (i, stake) in [100u64, 100, 100, 100].iter().enumerate() {
+            consensus.add_validator(ValidatorInfo::new(format!("v{i}"), *stake, Vec::new())).unwrap();
+        }
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert!(proofs.is_empty());
+        assert!(!consensus.is_final(&vertex.tx_hash));
+    }
+
+    #[test]
+    fn real_mode_rejects_unverifiable_votes() {
+        let config = ConsensusConfig {
+            mode: ConsensusMode::Real,
+            ..ConsensusConfig::default()
+        };
+        let mut consensus = VirtualVotingConsensus::new(config);
+        let key = SecretKey::key_gen(&[9u8; 32], &[]).unwrap();
+        consensus.add_validator_with_key(ValidatorInfo::new("v0".into(), 100, Vec::new()), key).unwrap();
+        let vertex = sample_vertex(1);
+        let vote = VirtualVote {
+            validator_id: "v0".into(),
+            vertex_hash: vertex.tx_hash,
+            round: 1,
+            approve: true,
+            timestamp: now_millis(),
+            signature: vec![0u8; 48],
+        };
+        assert!(consensus.submit_vote(vote).is_err());
+    }
+
+    #[test]
+    fn a_capped_whale_cannot_finalize_alone_but_a_coalition_can() {
+        let config = ConsensusConfig {
+            mode: ConsensusMode::Real,
+            max_vote_weight_fraction: 0.33,
+            ..ConsensusConfig::default()
+        };
+        let mut consensus = VirtualVotingConsensus::new(config);
+        let whale_key = SecretKey::key_gen(&[1u8; 32], &[]).unwrap();
+        consensus
+            .add_validator_with_key(
+                ValidatorInfo::new("whale".into(), 70_000, Vec::new()),
+                whale_key,
+            )
+            .unwrap();
+        let mut keys = Vec::new();
+        for (i, id) in ["v1", "v2"].iter().enumerate() {
+            let key = SecretKey::key_gen(&[i as u8 + 2; 32], &[]).unwrap();
+            consensus
+                .add_validator(ValidatorInfo::new(
+                    (*id).into(),
+                    15_000,
+                    key.sk_to_pk().to_bytes().to_vec(),
+                ))
+                .unwrap();
+            keys.push(key);
+        }
+
+        // Capped at 33% of the raw stake, the whale's 70k counts as 33k of
+        // an effective 63k total, short of the threshold it would clear
+        // uncapped.
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert!(proofs.is_empty());
+        let record = consensus.get_vote_record(&vertex.tx_hash).unwrap();
+        assert_eq!(record.voting_stake, 33_000);
+        assert!(record.voting_stake < consensus.required_stake());
+
+        // The two 15k validators tip the coalition past the threshold.
+        let round = consensus.current_round();
+        for (i, id) in ["v1", "v2"].iter().enumerate() {
+            let signature = keys[i]
+                .sign(&vote_message(&vertex.tx_hash, round), VOTE_DST, &[])
+                .to_bytes()
+                .to_vec();
+            consensus
+                .submit_vote(VirtualVote {
+                    validator_id: (*id).into(),
+                    vertex_hash: vertex.tx_hash,
+                    round,
+                    approve: true,
+                    timestamp: now_millis(),
+                    signature,
+                })
+                .unwrap();
+        }
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(proofs[0].supporting_stake, 63_000);
+        assert!(consensus.is_final(&vertex.tx_hash));
+    }
+
+    #[test]
+    fn a_silent_validators_stake_drops_out_of_quorum_until_it_heartbeats() {
+        let config = ConsensusConfig {
+            mode: ConsensusMode::Real,
+            validator_liveness_timeout_ms: 1_000,
+            ..ConsensusConfig::default()
+        };
+        let mut consensus = VirtualVotingConsensus::new(config);
+        consensus
+            .add_validator(ValidatorInfo::new("silent".into(), 1_000, Vec::new()))
+            .unwrap();
+        for (i, id) in ["v1", "v2"].iter().enumerate() {
+            let key = SecretKey::key_gen(&[i as u8 + 7; 32], &[]).unwrap();
+            consensus
+                .add_validator_with_key(ValidatorInfo::new((*id).into(), 100, Vec::new()), key)
+                .unwrap();
+        }
+
+        // While the dead validator's 1k still counts, the live 200 cannot
+        // clear the threshold and finality stalls.
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert!(proofs.is_empty());
+
+        // Past the timeout its stake leaves the quorum math and the
+        // remaining set suffices.
+        consensus.validators.get_mut("silent").unwrap().last_activity =
+            now_millis() - 2_000;
+        assert_eq!(consensus.active_stake(), 200);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(proofs[0].supporting_stake, 200);
+
+        // A heartbeat restores it immediately.
+        consensus.record_heartbeat("silent");
+        assert_eq!(consensus.active_stake(), 1_200);
+    }
+
+    #[test]
+    fn replayed_and_out_of_window_votes_are_not_counted() {
+        let mut consensus = VirtualVotingConsensus::new(ConsensusConfig::default());
+        consensus.add_validator(ValidatorInfo::new("v0".into(), 100, Vec::new())).unwrap();
+        let vertex = sample_vertex(1);
+        let vote = VirtualVote {
+            validator_id: "v0".into(),
+            vertex_hash: vertex.tx_hash,
+            round: 1,
+            approve: true,
+            timestamp: now_millis(),
+            signature: vec![0u8; 48],
+        };
+
+        consensus.submit_vote(vote.clone()).unwrap();
+        // Replaying the same vote is ignored and does not inflate the tally.
+        consensus.submit_vote(vote.clone()).unwrap();
+        let record = consensus.get_vote_record(&vertex.tx_hash).unwrap();
+        assert_eq!(record.approvals, 1);
+        assert_eq!(record.voting_stake, 100);
+
+        // A vote for a round far in the future is rejected outright.
+        let mut future = vote.clone();
+        future.round = consensus.current_round()
+            + consensus.config.vote_round_window
+            + 1;
+        assert!(consensus.submit_vote(future).is_err());
+
+        // Once the current round passes the window, the old vote is stale.
+        for _ in 0..consensus.config.vote_round_window + 2 {
+            consensus.process_consensus_round(&[]);
+        }
+        let mut stale = vote;
+        stale.validator_id = "v0".into();
+        assert!(consensus.submit_vote(stale).is_err());
+    }
+
+    #[test]
+    fn aggregated_proof_verifies() {
+        let mut consensus = consensus_with_keyed_validators(&[100, 100, 100, 100]);
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert_eq!(proofs.len(), 1);
+        let proof = &proofs[0];
+        assert_eq!(proof.aggregate.vote_count, 4);
+        assert!(consensus.verify_finality_proof(proof));
+
+        // Flipping a participant bit changes the aggregate public key.
+        let mut tampered = proof.clone();
+        tampered.aggregate.participants[0] ^= 1;
+        assert!(!consensus.verify_finality_proof(&tampered));
+    }
+
+    #[test]
+    fn aggregated_proof_is_smaller_than_vote_list() {
+        let mut consensus = consensus_with_keyed_validators(&[100; 8]);
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        let proof_size = bincode::serialize(&proofs[0]).unwrap().len();
+        let votes = &consensus.get_vote_record(&vertex.tx_hash).unwrap().votes;
+        let votes_size = bincode::serialize(&votes.values().collect::<Vec<_>>())
+            .unwrap()
+            .len();
+        assert!(
+            proof_size < votes_size / 2,
+            "aggregated proof ({proof_size} bytes) should be well under the \
+             vote list ({votes_size} bytes)"
+        );
+    }
+
+    #[test]
+    fn unkeyed_validators_yield_unverifiable_proof() {
+        let mut consensus = consensus_with_validators(&[100, 100, 100, 100]);
+        let vertex = sample_vertex(1);
+        let proofs = consensus.process_consensus_round(std::slice::from_ref(&vertex));
+        assert_eq!(proofs.len(), 1);
+        assert!(proofs[0].aggregate.aggregate_signature.is_empty());
+        assert!(!consensus.verify_finality_proof(&proofs[0]));
+    }
+
+    #[test]
+    fn finalized_order_is_deterministic_across_runs() {
+        let mut vertices = Vec::new();
+        for i in 0..6u64 {
+            let tx = TransactionData {
+                source: "a".into(),
+                target: "b".into(),
+                amount: 10,
+                currency: 1,
+                nonce: i,
+                fee: 1,
+                user_data: Vec::new(),
+                outputs: Vec::new(),
+            };
+            // Repeated clocks force the hash tie-break to matter.
+            vertices.push(DAGVertex::new(tx, Vec::new(), i / 2 + 1, 0));
+        }
+
+        let mut first = consensus_with_validators(&[100, 100, 100]);
+        let ordered_first = {
+            first.process_consensus_round(&vertices);
+            first.finalized_order(1)
+        };
+
+        // Insert the candidates (and validators) in a different order.
+        let mut second = consensus_with_validators(&[100, 100, 100]);
+        let mut reversed = vertices.clone();
+        reversed.reverse();
+        second.process_consensus_round(&reversed);
+
+        assert_eq!(ordered_first.len(), vertices.len());
+        assert_eq!(ordered_first, second.finalized_order(1));
+        let clocks: Vec<u64> = ordered_first
+            .iter()
+            .map(|h| vertices.iter().find(|v| v.tx_hash == *h).unwrap().logical_clock)
+            .collect();
+        assert!(clocks.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn checkpoint_produced_after_interval_and_verifies() {
+        let mut consensus = consensus_with_keyed_validators(&[100, 100, 100]);
+        consensus.config.checkpoint_interval = 2;
+        assert!(consensus.latest_checkpoint().is_none());
+
+        let first = sample_vertex(1);
+        consensus.process_consensus_round(std::slice::from_ref(&first));
+        assert!(consensus.latest_checkpoint().is_none());
+
+        let second = sample_vertex(2);
+        consensus.process_consensus_round(std::slice::from_ref(&second));
+        let checkpoint = consensus.latest_checkpoint().expect("checkpoint").clone();
+        assert_eq!(checkpoint.clock, 2);
+        assert_eq!(checkpoint.aggregate.vote_count, 3);
+        assert!(consensus.verify_checkpoint(&checkpoint));
+
+        // A doctored state root must not verify.
+        let mut tampered = checkpoint;
+        tampered.state_root[0] ^= 1;
+        assert!(!consensus.verify_checkpoint(&tampered));
+    }
+
+    #[test]
+    fn tier_assignment_follows_stake() {
+        let genesis = ValidatorInfo::new("g".into(), 500_000_000_000, Vec::new());
+        let staked = ValidatorInfo::new("s".into(), 50_000_000_000, Vec::new());
+        let community = ValidatorInfo::new("c".into(), 1_000, Vec::new());
+        assert_eq!(genesis.tier, ValidatorTier::Genesis);
+        assert_eq!(staked.tier, ValidatorTier::Staked);
+        assert_eq!(community.tier, ValidatorTier::Community);
+    }
+
+    #[test]
+    fn required_votes_tolerates_f_faults() {
+        let consensus = consensus_with_validators(&[1, 1, 1, 1]);
+        assert_eq!(consensus.required_votes(), 3);
+    }
+
+    #[test]
+    fn bft_threshold_drives_quorum_requirements() {
+        let mut consensus = consensus_with_validators(&[100, 100, 100, 100]);
+        let default_stake = consensus.required_stake();
+
+        consensus.config.bft_threshold = 0.75;
+        assert_eq!(consensus.required_stake(), 301);
+        assert!(consensus.required_stake() > default_stake);
+        assert_eq!(consensus.required_votes(), 3);
+
+        consensus.config.bft_threshold = 0.9;
+        assert_eq!(consensus.required_stake(), 361);
+        assert_eq!(consensus.required_votes(), 4);
+    }
+
+    #[test]
+    fn unsafe_bft_threshold_falls_back_to_default() {
+        let consensus = VirtualVotingConsensus::new(ConsensusConfig {
+            bft_threshold: 0.5,
+            ..ConsensusConfig::default()
+        });
+        assert_eq!(
+            consensus.config.bft_threshold,
+            ConsensusConfig::default().bft_threshold
+        );
+    }
+
+    #[test]
+    fn stake_distribution_groups_by_tier() {
+        let mut consensus = VirtualVotingConsensus::new(ConsensusConfig::default());
+        consensus.add_validator(ValidatorInfo::new("g".into(), 500_000_000_000, Vec::new())).unwrap();
+        consensus.add_validator(ValidatorInfo::new("s1".into(), 50_000_000_000, Vec::new())).unwrap();
+        consensus.add_validator(ValidatorInfo::new("s2".into(), 60_000_000_000, Vec::new())).unwrap();
+        consensus.add_validator(ValidatorInfo::new("c".into(), 1_000, Vec::new())).unwrap();
+
+        let distribution = consensus.stake_distribution();
+        assert_eq!(distribution[&ValidatorTier::Genesis], (1, 500_000_000_000));
+        assert_eq!(distribution[&ValidatorTier::Staked], (2, 110_000_000_000));
+        assert_eq!(distribution[&ValidatorTier::Community], (1, 1_000));
+
+        let listed = consensus.list_validators();
+        assert_eq!(listed.len(), 4);
+        assert!(listed.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn non_participating_validator_decays_and_is_pruned() {
+        let mut consensus = consensus_with_keyed_validators(&[100, 100, 100]);
+        consensus.config.epoch_length = 5;
+        // A validator with no signing key never participates in rounds.
+        consensus.add_validator(ValidatorInfo::new("lazy".into(), 100, Vec::new())).unwrap();
+        assert_eq!(consensus.active_stake(), 400);
+
+        let mut excluded_at = None;
+        for round in 0..20u64 {
+            let vertex = sample_vertex(round + 1);
+            consensus.process_consensus_round(std::slice::from_ref(&vertex));
+            if let Some(lazy) = consensus.get_validator("lazy") {
+                assert!(lazy.performance_score < 1.0);
+                if lazy.performance_score < MIN_PERFORMANCE_SCORE && excluded_at.is_none() {
+                    excluded_at = Some(round);
+                    // Below the threshold it no longer counts toward quorum.
+                    assert_eq!(consensus.active_stake(), 300);
+                }
+            }
+        }
+        assert!(excluded_at.is_some());
+        // The next epoch boundary after the threshold crossing removes it.
+        assert!(consensus.get_validator("lazy").is_none());
+        assert_eq!(consensus.validator_count(), 3);
+    }
+}
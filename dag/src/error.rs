@@ -0,0 +1,67 @@
+//! Error types shared across the DAG engine, storage, network and FFI layers.
+
+use thiserror::Error;
+
+/// Unified error type for all DAG node operations.
+#[derive(Debug, Error)]
+pub enum DAGError {
+    /// The vertex is structurally invalid (bad parents, bad clock, bad shape).
+    #[error("invalid vertex: {0}")]
+    InvalidVertex(String),
+
+    /// A validation rule rejected the vertex or transaction.
+    #[error("validation error: {0}")]
+    ValidationError(String),
+
+    /// The underlying storage backend failed.
+    #[error("storage error: {0}")]
+    StorageError(String),
+
+    /// A network operation failed.
+    #[error("network error: {0}")]
+    NetworkError(String),
+
+    /// Serialization or deserialization failed.
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+
+    /// The consensus layer reported a failure.
+    #[error("consensus error: {0}")]
+    ConsensusError(String),
+
+    /// A cryptographic signature did not verify.
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    /// The vertex or record already exists.
+    #[error("already exists: {0}")]
+    AlreadyExists(String),
+
+    /// The requested vertex, account or record does not exist.
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    /// An account balance cannot cover the requested debit.
+    #[error("insufficient balance: {0}")]
+    InsufficientBalance(String),
+
+    /// A transaction nonce does not match the account's expected nonce.
+    #[error("nonce mismatch: expected {expected}, got {got}")]
+    NonceMismatch { expected: u64, got: u64 },
+
+    /// A transaction fee is below the mempool admission floor.
+    #[error("insufficient fee: {0}")]
+    InsufficientFee(String),
+}
+
+impl From<bincode::Error> for DAGError {
+    fn from(e: bincode::Error) -> Self {
+        DAGError::SerializationError(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for DAGError {
+    fn from(e: std::io::Error) -> Self {
+        DAGError::StorageError(e.to_string())
+    }
+}
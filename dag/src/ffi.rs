@@ -0,0 +1,480 @@
+//! C ABI for embedding the DAG engine in the C++ node.
+//!
+//! All functions are panic-free and report failures through [`DAGErrorCode`].
+
+use std::ffi::{c_char, CStr};
+use std::path::PathBuf;
+
+use crate::engine::{DAGEngine, DAGEngineConfig};
+use crate::error::DAGError;
+use crate::vertex::{DAGVertex, VertexHash};
+
+/// Error codes returned across the FFI boundary.
+///
+/// Values are part of the ABI; append only.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DAGErrorCode {
+    Success = 0,
+    InvalidVertex = 1,
+    ValidationError = 2,
+    StorageError = 3,
+    NetworkError = 4,
+    SerializationError = 5,
+    ConsensusError = 6,
+    InvalidSignature = 7,
+    InternalError = 8,
+    AlreadyExists = 9,
+    NotFound = 10,
+    InsufficientBalance = 11,
+    NonceMismatch = 12,
+    InsufficientFee = 13,
+}
+
+impl From<&DAGError> for DAGErrorCode {
+    fn from(e: &DAGError) -> Self {
+        match e {
+            DAGError::InvalidVertex(_) => DAGErrorCode::InvalidVertex,
+            DAGError::ValidationError(_) => DAGErrorCode::ValidationError,
+            DAGError::StorageError(_) => DAGErrorCode::StorageError,
+            DAGError::NetworkError(_) => DAGErrorCode::NetworkError,
+            DAGError::SerializationError(_) => DAGErrorCode::SerializationError,
+            DAGError::ConsensusError(_) => DAGErrorCode::ConsensusError,
+            DAGError::InvalidSignature => DAGErrorCode::InvalidSignature,
+            DAGError::AlreadyExists(_) => DAGErrorCode::AlreadyExists,
+            DAGError::NotFound(_) => DAGErrorCode::NotFound,
+            DAGError::InsufficientBalance(_) => DAGErrorCode::InsufficientBalance,
+            DAGError::NonceMismatch { .. } => DAGErrorCode::NonceMismatch,
+            DAGError::InsufficientFee(_) => DAGErrorCode::InsufficientFee,
+        }
+    }
+}
+
+/// Opaque engine handle owned by the C caller.
+pub struct DAGEngineHandle {
+    engine: DAGEngine,
+}
+
+/// Returns a static, nul-terminated description for an error code.
+#[no_mangle]
+pub extern "C" fn dag_error_message(code: DAGErrorCode) -> *const c_char {
+    let message: &'static [u8] = match code {
+        DAGErrorCode::Success => b"success\0",
+        DAGErrorCode::InvalidVertex => b"invalid vertex\0",
+        DAGErrorCode::ValidationError => b"validation error\0",
+        DAGErrorCode::StorageError => b"storage error\0",
+        DAGErrorCode::NetworkError => b"network error\0",
+        DAGErrorCode::SerializationError => b"serialization error\0",
+        DAGErrorCode::ConsensusError => b"consensus error\0",
+        DAGErrorCode::InvalidSignature => b"invalid signature\0",
+        DAGErrorCode::InternalError => b"internal error\0",
+        DAGErrorCode::AlreadyExists => b"already exists\0",
+        DAGErrorCode::NotFound => b"not found\0",
+        DAGErrorCode::InsufficientBalance => b"insufficient balance\0",
+        DAGErrorCode::NonceMismatch => b"nonce mismatch\0",
+        DAGErrorCode::InsufficientFee => b"insufficient fee\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+/// Creates a new engine rooted at `data_dir`. Returns null on failure.
+///
+/// # Safety
+///
+/// `data_dir` must be a valid nul-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_new(data_dir: *const c_char) -> *mut DAGEngineHandle {
+    if data_dir.is_null() {
+        return std::ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(data_dir).to_str() else {
+        return std::ptr::null_mut();
+    };
+    let config = DAGEngineConfig {
+        data_dir: PathBuf::from(path),
+        ..DAGEngineConfig::default()
+    };
+    match DAGEngine::new(config) {
+        Ok(engine) => Box::into_raw(Box::new(DAGEngineHandle { engine })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Frees an engine handle created by [`dag_engine_new`].
+///
+/// # Safety
+///
+/// `handle` must be a pointer previously returned by [`dag_engine_new`] and
+/// not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_free(handle: *mut DAGEngineHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Inserts a bincode-serialized vertex.
+///
+/// # Safety
+///
+/// `handle` must be a live engine handle; `bytes` must point to `len`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_insert_vertex(
+    handle: *mut DAGEngineHandle,
+    bytes: *const u8,
+    len: usize,
+) -> DAGErrorCode {
+    if handle.is_null() || bytes.is_null() {
+        return DAGErrorCode::InternalError;
+    }
+    let slice = std::slice::from_raw_parts(bytes, len);
+    let vertex: DAGVertex = match bincode::deserialize(slice) {
+        Ok(v) => v,
+        Err(_) => return DAGErrorCode::SerializationError,
+    };
+    match (*handle).engine.insert_vertex(vertex) {
+        Ok(()) => DAGErrorCode::Success,
+        Err(e) => DAGErrorCode::from(&e),
+    }
+}
+
+/// Fetches a vertex by hash into `out_buf` as bincode, writing the length to
+/// `out_len`.
+///
+/// # Safety
+///
+/// `handle` must be a live engine handle, `hash` must point to 32 readable
+/// bytes, `out_buf` to `buf_len` writable bytes and `out_len` to a writable
+/// `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_get_vertex(
+    handle: *mut DAGEngineHandle,
+    hash: *const u8,
+    out_buf: *mut u8,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> DAGErrorCode {
+    if handle.is_null() || hash.is_null() || out_buf.is_null() || out_len.is_null() {
+        return DAGErrorCode::InternalError;
+    }
+    let hash_slice = std::slice::from_raw_parts(hash, 32);
+    let hash_array: VertexHash = match hash_slice.try_into() {
+        Ok(h) => h,
+        Err(_) => return DAGErrorCode::InternalError,
+    };
+    match (*handle).engine.get_vertex(&hash_array) {
+        Ok(Some(vertex)) => {
+            let Ok(bytes) = bincode::serialize(&vertex) else {
+                return DAGErrorCode::SerializationError;
+            };
+            if bytes.len() > buf_len {
+                *out_len = bytes.len();
+                return DAGErrorCode::InternalError;
+            }
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), out_buf, bytes.len());
+            *out_len = bytes.len();
+            DAGErrorCode::Success
+        }
+        Ok(None) => DAGErrorCode::NotFound,
+        Err(e) => DAGErrorCode::from(&e),
+    }
+}
+
+/// Writes the canonical signing bytes of a bincode-serialized vertex into
+/// `out_buf`; see [`DAGVertex::canonical_signing_bytes`] for the layout. If
+/// the buffer is too small, `out_len` receives the required size and
+/// `InternalError` is returned.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes, `out_buf` to `buf_len`
+/// writable bytes and `out_len` to a writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn dag_vertex_signing_bytes(
+    bytes: *const u8,
+    len: usize,
+    out_buf: *mut u8,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> DAGErrorCode {
+    if bytes.is_null() || out_buf.is_null() || out_len.is_null() {
+        return DAGErrorCode::InternalError;
+    }
+    let slice = std::slice::from_raw_parts(bytes, len);
+    let Ok(vertex) = bincode::deserialize::<DAGVertex>(slice) else {
+        return DAGErrorCode::SerializationError;
+    };
+    let payload = vertex.canonical_signing_bytes();
+    if payload.len() > buf_len {
+        *out_len = payload.len();
+        return DAGErrorCode::InternalError;
+    }
+    std::ptr::copy_nonoverlapping(payload.as_ptr(), out_buf, payload.len());
+    *out_len = payload.len();
+    DAGErrorCode::Success
+}
+
+/// Number of vertices in storage.
+///
+/// # Safety
+///
+/// `handle` must be a live engine handle.
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_vertex_count(handle: *mut DAGEngineHandle) -> u64 {
+    if handle.is_null() {
+        return 0;
+    }
+    (*handle).engine.vertex_count()
+}
+
+/// Scalar view of a finality proof for the C caller.
+///
+/// Field order is part of the ABI; append only.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CFinalityProof {
+    /// Consensus round the vertex finalized in.
+    pub round: u64,
+    /// Proof timestamp, milliseconds since the epoch.
+    pub timestamp: u64,
+    /// Number of supporting votes in the aggregate.
+    pub supporting_vote_count: u32,
+    /// Stake that voted for the vertex.
+    pub supporting_stake: u64,
+    /// Total active stake at proof time.
+    pub total_stake: u64,
+    /// 1 when the supporting stake strictly exceeds 2/3 of the total.
+    pub safety_satisfied: u8,
+}
+
+/// Writes a vertex's finality proof into `out`. Returns `NotFound` if the
+/// vertex has not finalized.
+///
+/// # Safety
+///
+/// `handle` must be a live engine handle, `hash` must point to 32 readable
+/// bytes and `out` to a writable [`CFinalityProof`].
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_get_finality_proof(
+    handle: *mut DAGEngineHandle,
+    hash: *const u8,
+    out: *mut CFinalityProof,
+) -> DAGErrorCode {
+    if handle.is_null() || hash.is_null() || out.is_null() {
+        return DAGErrorCode::InternalError;
+    }
+    let hash_slice = std::slice::from_raw_parts(hash, 32);
+    let hash_array: VertexHash = match hash_slice.try_into() {
+        Ok(h) => h,
+        Err(_) => return DAGErrorCode::InternalError,
+    };
+    match (*handle).engine.get_finality_proof(&hash_array) {
+        Some(proof) => {
+            *out = CFinalityProof {
+                round: proof.round,
+                timestamp: proof.timestamp,
+                supporting_vote_count: proof.aggregate.vote_count,
+                supporting_stake: proof.supporting_stake,
+                total_stake: proof.total_stake,
+                safety_satisfied: u8::from(proof.supporting_stake * 3 > proof.total_stake * 2),
+            };
+            DAGErrorCode::Success
+        }
+        None => DAGErrorCode::NotFound,
+    }
+}
+
+/// Whether a vertex has finalized: 1 yes, 0 no, -1 on a bad handle or hash.
+///
+/// # Safety
+///
+/// `handle` must be a live engine handle and `hash` must point to 32
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dag_engine_is_final(
+    handle: *mut DAGEngineHandle,
+    hash: *const u8,
+) -> std::ffi::c_int {
+    if handle.is_null() || hash.is_null() {
+        return -1;
+    }
+    let hash_slice = std::slice::from_raw_parts(hash, 32);
+    let hash_array: VertexHash = match hash_slice.try_into() {
+        Ok(h) => h,
+        Err(_) => return -1,
+    };
+    std::ffi::c_int::from((*handle).engine.is_final(&hash_array))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::TransactionData;
+    use std::ffi::CString;
+
+    fn sample_vertex() -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: 0,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, Vec::new(), 0, 0)
+    }
+
+    #[test]
+    fn ffi_insert_and_get_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        unsafe {
+            let handle = dag_engine_new(path.as_ptr());
+            assert!(!handle.is_null());
+
+            let vertex = sample_vertex();
+            let bytes = bincode::serialize(&vertex).unwrap();
+            let code = dag_engine_insert_vertex(handle, bytes.as_ptr(), bytes.len());
+            assert_eq!(code, DAGErrorCode::Success);
+            assert_eq!(dag_engine_vertex_count(handle), 1);
+
+            let mut buf = vec![0u8; 4096];
+            let mut written = 0usize;
+            let code = dag_engine_get_vertex(
+                handle,
+                vertex.tx_hash.as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut written,
+            );
+            assert_eq!(code, DAGErrorCode::Success);
+            let loaded: DAGVertex = bincode::deserialize(&buf[..written]).unwrap();
+            assert_eq!(loaded.tx_hash, vertex.tx_hash);
+
+            // A hash that was never inserted reports NotFound, not a
+            // validation failure.
+            let missing = [0xAAu8; 32];
+            let code = dag_engine_get_vertex(
+                handle,
+                missing.as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut written,
+            );
+            assert_eq!(code, DAGErrorCode::NotFound);
+
+            dag_engine_free(handle);
+        }
+    }
+
+    #[test]
+    fn finality_proof_is_readable_through_the_c_abi() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        unsafe {
+            let handle = dag_engine_new(path.as_ptr());
+            assert!(!handle.is_null());
+
+            let vertex = sample_vertex();
+            let bytes = bincode::serialize(&vertex).unwrap();
+            assert_eq!(
+                dag_engine_insert_vertex(handle, bytes.as_ptr(), bytes.len()),
+                DAGErrorCode::Success
+            );
+
+            // Not final yet.
+            assert_eq!(dag_engine_is_final(handle, vertex.tx_hash.as_ptr()), 0);
+            let mut proof = CFinalityProof::default();
+            assert_eq!(
+                dag_engine_get_finality_proof(handle, vertex.tx_hash.as_ptr(), &mut proof),
+                DAGErrorCode::NotFound
+            );
+
+            // Finalize in simulated mode with a single validator.
+            (*handle)
+                .engine
+                .consensus()
+                .write()
+                .unwrap()
+                .add_validator(crate::consensus::ValidatorInfo::new(
+                    "v0".into(),
+                    100,
+                    Vec::new(),
+                )).unwrap();
+            (*handle).engine.process_consensus_round().unwrap();
+
+            assert_eq!(dag_engine_is_final(handle, vertex.tx_hash.as_ptr()), 1);
+            assert_eq!(
+                dag_engine_get_finality_proof(handle, vertex.tx_hash.as_ptr(), &mut proof),
+                DAGErrorCode::Success
+            );
+            assert_eq!(proof.round, 1);
+            assert_eq!(proof.supporting_stake, 100);
+            assert_eq!(proof.total_stake, 100);
+            assert_eq!(proof.safety_satisfied, 1);
+
+            dag_engine_free(handle);
+        }
+    }
+
+    #[test]
+    fn specific_errors_map_to_specific_codes() {
+        let cases = [
+            (
+                DAGError::AlreadyExists("v".into()),
+                DAGErrorCode::AlreadyExists,
+            ),
+            (DAGError::NotFound("v".into()), DAGErrorCode::NotFound),
+            (
+                DAGError::InsufficientBalance("a".into()),
+                DAGErrorCode::InsufficientBalance,
+            ),
+            (
+                DAGError::NonceMismatch {
+                    expected: 2,
+                    got: 5,
+                },
+                DAGErrorCode::NonceMismatch,
+            ),
+            (
+                DAGError::InsufficientFee("f".into()),
+                DAGErrorCode::InsufficientFee,
+            ),
+        ];
+        for (error, code) in cases {
+            assert_eq!(DAGErrorCode::from(&error), code);
+            let msg = dag_error_message(code);
+            assert!(!unsafe { CStr::from_ptr(msg) }.to_str().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn duplicate_insert_reports_already_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        unsafe {
+            let handle = dag_engine_new(path.as_ptr());
+            let vertex = sample_vertex();
+            let bytes = bincode::serialize(&vertex).unwrap();
+            assert_eq!(
+                dag_engine_insert_vertex(handle, bytes.as_ptr(), bytes.len()),
+                DAGErrorCode::Success
+            );
+            assert_eq!(
+                dag_engine_insert_vertex(handle, bytes.as_ptr(), bytes.len()),
+                DAGErrorCode::AlreadyExists
+            );
+            dag_engine_free(handle);
+        }
+    }
+
+    #[test]
+    fn error_messages_are_nul_terminated() {
+        let msg = dag_error_message(DAGErrorCode::StorageError);
+        let text = unsafe { CStr::from_ptr(msg) }.to_str().unwrap();
+        assert_eq!(text, "storage error");
+    }
+}
@@ -0,0 +1,163 @@
+//! Persistent node identity: an Ed25519 keypair that outlives restarts.
+//!
+//! The wallet key signs transactions; this key authenticates the node on
+//! the network. The node id is derived from the public key, so peers can
+//! recognize a node across restarts and verify handshake signatures.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use crate::error::DAGError;
+
+/// A node's long-lived Ed25519 identity keypair.
+pub struct NodeIdentity {
+    signing_key: SigningKey,
+    node_id: String,
+}
+
+impl NodeIdentity {
+    /// Generates a fresh random identity.
+    pub fn generate() -> Self {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    /// Generates an identity from the supplied RNG; with a seeded RNG the
+    /// resulting keypair is reproducible.
+    pub fn generate_with_rng<R: rand::RngCore + rand::CryptoRng>(rng: &mut R) -> Self {
+        Self::from_secret_bytes(&SigningKey::generate(rng).to_bytes())
+    }
+
+    /// Restores an identity from raw secret key bytes.
+    pub fn from_secret_bytes(bytes: &[u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(bytes);
+        let node_id = Self::derive_node_id(signing_key.verifying_key().as_bytes());
+        NodeIdentity {
+            signing_key,
+            node_id,
+        }
+    }
+
+    /// Loads the identity at `path`, creating and persisting a new one on
+    /// first start.
+    pub fn load_or_create(path: &std::path::Path) -> Result<Self, DAGError> {
+        Self::load_or_create_with_rng(path, &mut OsRng)
+    }
+
+    /// [`load_or_create`](Self::load_or_create) with a caller-supplied RNG
+    /// for the first-start case, so simulations can seed it.
+    pub fn load_or_create_with_rng<R: rand::RngCore + rand::CryptoRng>(
+        path: &std::path::Path,
+        rng: &mut R,
+    ) -> Result<Self, DAGError> {
+        if path.exists() {
+            let hex_str = std::fs::read_to_string(path)?;
+            let bytes = hex::decode(hex_str.trim())
+                .map_err(|e| DAGError::SerializationError(format!("bad node key file: {e}")))?;
+            let secret: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| DAGError::SerializationError("node key must be 32 bytes".into()))?;
+            Ok(Self::from_secret_bytes(&secret))
+        } else {
+            let identity = Self::generate_with_rng(rng);
+            write_owner_only(path, &hex::encode(identity.signing_key.to_bytes()))?;
+            Ok(identity)
+        }
+    }
+
+    /// Node id: hex-encoded SHA-256 of the public key, truncated to 20 bytes.
+    pub fn derive_node_id(public_key: &[u8]) -> String {
+        let digest = Sha256::digest(public_key);
+        hex::encode(&digest[..20])
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn public_key_bytes(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// Signs a handshake nonce with the identity key.
+    pub fn sign_nonce(&self, nonce: u64) -> Vec<u8> {
+        self.signing_key.sign(&nonce.to_le_bytes()).to_vec()
+    }
+
+    /// Verifies a peer's signature over a handshake nonce.
+    pub fn verify_nonce(public_key: &[u8], nonce: u64, signature: &[u8]) -> bool {
+        let Ok(key_bytes) = <[u8; 32]>::try_from(public_key) else {
+            return false;
+        };
+        let Ok(key) = VerifyingKey::from_bytes(&key_bytes) else {
+            return false;
+        };
+        let Ok(sig_bytes) = <[u8; 64]>::try_from(signature) else {
+            return false;
+        };
+        key.verify(&nonce.to_le_bytes(), &Signature::from_bytes(&sig_bytes))
+            .is_ok()
+    }
+}
+
+/// Writes key material to a file readable only by the owner (0o600 on
+/// unix), applying the mode before any secret bytes land on disk and
+/// tightening it when overwriting an existing file.
+pub(crate) fn write_owner_only(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_id_is_stable_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node_key");
+        let first = NodeIdentity::load_or_create(&path).unwrap();
+        let second = NodeIdentity::load_or_create(&path).unwrap();
+        assert_eq!(first.node_id(), second.node_id());
+        assert_eq!(first.public_key_bytes(), second.public_key_bytes());
+        assert_eq!(first.node_id().len(), 40);
+    }
+
+    #[test]
+    fn handshake_signatures_verify() {
+        let identity = NodeIdentity::generate();
+        let signature = identity.sign_nonce(42);
+        assert!(NodeIdentity::verify_nonce(
+            &identity.public_key_bytes(),
+            42,
+            &signature
+        ));
+        // A different nonce or key does not verify.
+        assert!(!NodeIdentity::verify_nonce(
+            &identity.public_key_bytes(),
+            43,
+            &signature
+        ));
+        let other = NodeIdentity::generate();
+        assert!(!NodeIdentity::verify_nonce(
+            &other.public_key_bytes(),
+            42,
+            &signature
+        ));
+    }
+}
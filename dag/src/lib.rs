@@ -0,0 +1,28 @@
+//! dag-node: an experimental DAG ledger with virtual-voting consensus.
+//!
+//! The crate is consumed three ways: as the `dag-node` binary, as a library
+//! for tests and tooling, and as a `cdylib` linked into the C++ node via the
+//! [`ffi`] module.
+
+pub mod consensus;
+pub mod engine;
+pub mod error;
+pub mod ffi;
+pub mod identity;
+pub mod logging;
+pub mod mempool;
+pub mod metrics;
+pub mod network;
+pub mod node;
+pub mod rpc;
+pub mod shard;
+pub mod state;
+pub mod storage;
+#[cfg(feature = "sled-backend")]
+pub mod storage_lsm;
+#[cfg(feature = "rocksdb-backend")]
+pub mod storage_rocksdb;
+pub mod storage_unified;
+pub mod validation;
+pub mod vertex;
+pub mod wallet;
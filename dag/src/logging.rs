@@ -0,0 +1,126 @@
+//! Logging setup: stderr by default, size-rotated files when configured.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::node::NodeConfig;
+
+/// An append-only log file that rotates when it reaches a size limit.
+///
+/// Rotation renames `node.log` to `node.log.1`, shifting older files up to
+/// `node.log.<max_files>`, which is dropped. Writes always go to the base
+/// path, so `tail -f` keeps working across rotations.
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFileWriter {
+    pub fn open(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFileWriter {
+            path: path.to_path_buf(),
+            max_size,
+            max_files: max_files.max(1),
+            file,
+            written,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated_path(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() as u64 > self.max_size && self.written > 0 {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Initializes the global logger from the node configuration.
+///
+/// Honors `log_level` (overridable via `RUST_LOG`); when `log_file` is set,
+/// log lines go to that file with rotation instead of stderr.
+pub fn init(config: &NodeConfig) -> Result<(), io::Error> {
+    let env = env_logger::Env::default().default_filter_or(config.log_level.clone());
+    let mut builder = env_logger::Builder::from_env(env);
+    if let Some(path) = &config.log_file {
+        let writer =
+            RotatingFileWriter::open(path, config.log_max_size_bytes, config.log_max_files)?;
+        builder.target(env_logger::Target::Pipe(Box::new(writer)));
+    }
+    builder.init();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_written_to_the_configured_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.log");
+        let mut writer = RotatingFileWriter::open(&path, 1024, 3).unwrap();
+        writeln!(writer, "hello").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn rotation_triggers_at_the_size_limit_and_keeps_n_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.log");
+        let mut writer = RotatingFileWriter::open(&path, 64, 2).unwrap();
+        for i in 0..20 {
+            writeln!(writer, "line {i} padding padding padding").unwrap();
+        }
+        writer.flush().unwrap();
+        assert!(path.exists());
+        assert!(dir.path().join("node.log.1").exists());
+        assert!(dir.path().join("node.log.2").exists());
+        // Only max_files rotated files are kept.
+        assert!(!dir.path().join("node.log.3").exists());
+        // The active file stays under the limit plus one line.
+        assert!(std::fs::metadata(&path).unwrap().len() <= 64 + 40);
+    }
+}
@@ -0,0 +1,149 @@
+//! dag-node binary: parses arguments, starts the node and runs the
+//! interactive CLI.
+
+use std::sync::Arc;
+
+use dag_node::node::{
+    run_exec_command, run_interactive_cli, BlockchainNode, NodeConfig, OutputMode,
+};
+
+fn print_usage() {
+    eprintln!("usage: dag-node [options]");
+    eprintln!("  --data-dir <path>      data directory (default ./dag-data)");
+    eprintln!("  --listen-addr <ip>     p2p bind address (default 0.0.0.0)");
+    eprintln!("  --port <port>          p2p listen port (default 9000)");
+    eprintln!("  --rpc-bind <ip>        rpc bind address (default 0.0.0.0)");
+    eprintln!("  --rpc-port <port>      rpc listen port (default 8080)");
+    eprintln!("  --peer <addr>          bootstrap peer (repeatable)");
+    eprintln!("  --stake <amount>       register as validator with stake");
+    eprintln!("  --validator-id <id>    stable validator identity");
+    eprintln!("  --mine                 enable mining");
+    eprintln!("  --log-file <path>      write rotated logs to a file instead of stderr");
+    eprintln!("  --json                 emit responses as raw JSON");
+    eprintln!("  --exec <command>       run one command and exit");
+    eprintln!("  --help                 show this help");
+}
+
+struct CliOptions {
+    config: NodeConfig,
+    mode: OutputMode,
+    exec: Option<String>,
+}
+
+fn parse_args() -> Result<CliOptions, String> {
+    let mut config = NodeConfig::default();
+    let mut mode = OutputMode::Text;
+    let mut exec = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--data-dir" => {
+                config.data_dir = args.next().ok_or("--data-dir needs a value")?.into();
+            }
+            "--listen-addr" => {
+                config.listen_addr = args
+                    .next()
+                    .ok_or("--listen-addr needs a value")?
+                    .parse()
+                    .map_err(|e| format!("bad listen address: {e}"))?;
+            }
+            "--rpc-bind" => {
+                config.rpc_bind_addr = args
+                    .next()
+                    .ok_or("--rpc-bind needs a value")?
+                    .parse()
+                    .map_err(|e| format!("bad rpc bind address: {e}"))?;
+            }
+            "--port" => {
+                config.port = args
+                    .next()
+                    .ok_or("--port needs a value")?
+                    .parse()
+                    .map_err(|e| format!("bad port: {e}"))?;
+            }
+            "--rpc-port" => {
+                config.rpc_port = args
+                    .next()
+                    .ok_or("--rpc-port needs a value")?
+                    .parse()
+                    .map_err(|e| format!("bad rpc port: {e}"))?;
+            }
+            "--peer" => {
+                let addr = args
+                    .next()
+                    .ok_or("--peer needs a value")?
+                    .parse()
+                    .map_err(|e| format!("bad peer address: {e}"))?;
+                config.bootstrap_peers.push(addr);
+            }
+            "--stake" => {
+                config.stake = args
+                    .next()
+                    .ok_or("--stake needs a value")?
+                    .parse()
+                    .map_err(|e| format!("bad stake: {e}"))?;
+            }
+            "--validator-id" => {
+                config.validator_id = Some(args.next().ok_or("--validator-id needs a value")?);
+            }
+            "--mine" => {
+                config.mining_enabled = true;
+            }
+            "--log-file" => {
+                config.log_file = Some(args.next().ok_or("--log-file needs a value")?.into());
+            }
+            "--json" => {
+                mode = OutputMode::Json;
+            }
+            "--exec" => {
+                exec = Some(args.next().ok_or("--exec needs a command")?);
+            }
+            "--help" | "-h" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            other => return Err(format!("unknown argument: {other}")),
+        }
+    }
+    Ok(CliOptions { config, mode, exec })
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {e}");
+            print_usage();
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = dag_node::logging::init(&options.config) {
+        eprintln!("failed to open log file: {e}");
+        std::process::exit(1);
+    }
+
+    // Construction rebuilds storage indices, which can take a while on a
+    // large database; keep that work off the async reactor.
+    let node = match tokio::task::spawn_blocking(move || BlockchainNode::new(options.config))
+        .await
+        .expect("node construction task panicked")
+    {
+        Ok(node) => Arc::new(node),
+        Err(e) => {
+            eprintln!("failed to initialize node: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = node.start().await {
+        eprintln!("failed to start node: {e}");
+        std::process::exit(1);
+    }
+
+    if let Some(command) = options.exec {
+        let code = run_exec_command(node.clone(), &command, options.mode).await;
+        node.stop();
+        std::process::exit(code);
+    }
+    run_interactive_cli(node, options.mode).await;
+}
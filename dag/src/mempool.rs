@@ -0,0 +1,325 @@
+//! Fee-prioritized mempool for pending transactions.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::DAGError;
+use crate::vertex::{PayloadType, TransactionData};
+
+/// Identifier of a mempool entry: SHA-256 of the serialized transaction.
+pub type TxId = [u8; 32];
+
+/// A queued transaction.
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    pub tx: TransactionData,
+    pub fee: u64,
+    pub added_at: Instant,
+}
+
+/// Mempool configuration.
+#[derive(Debug, Clone)]
+pub struct MempoolConfig {
+    /// Maximum number of queued transactions.
+    pub max_size: usize,
+    /// Minimum fee for admission, in the smallest CS unit.
+    pub min_tx_fee: u64,
+    /// Seconds an entry may wait before it is dropped; 0 disables expiry.
+    pub tx_ttl_secs: u64,
+    /// Per-payload-type priority multipliers for mempool ordering.
+    pub type_priority: TypePriority,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        MempoolConfig {
+            max_size: 10_000,
+            min_tx_fee: 1_000,
+            tx_ttl_secs: 3_600,
+            type_priority: TypePriority::default(),
+        }
+    }
+}
+
+/// Priority multipliers per payload type. An entry's effective priority is
+/// `fee * multiplier`, so values above 1.0 favor a type at equal fee and
+/// values below 1.0 deprioritize it; all-1.0 reproduces pure fee ordering.
+#[derive(Debug, Clone)]
+pub struct TypePriority {
+    pub transfer: f64,
+    pub cns: f64,
+    pub ordinal: f64,
+    pub raw: f64,
+}
+
+impl Default for TypePriority {
+    fn default() -> Self {
+        TypePriority {
+            transfer: 1.0,
+            cns: 1.0,
+            ordinal: 1.0,
+            raw: 1.0,
+        }
+    }
+}
+
+impl TypePriority {
+    /// Multiplier for a payload classification.
+    pub fn multiplier(&self, payload: PayloadType) -> f64 {
+        match payload {
+            PayloadType::Transfer => self.transfer,
+            PayloadType::Cns => self.cns,
+            PayloadType::Ordinal => self.ordinal,
+            PayloadType::Raw => self.raw,
+        }
+    }
+}
+
+/// Thread-safe fee-prioritized mempool.
+pub struct Mempool {
+    config: MempoolConfig,
+    entries: RwLock<HashMap<TxId, MempoolEntry>>,
+    /// Entries dropped because they outlived the TTL.
+    expired_total: RwLock<u64>,
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Mempool {
+            config,
+            entries: RwLock::new(HashMap::new()),
+            expired_total: RwLock::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &MempoolConfig {
+        &self.config
+    }
+
+    fn tx_id(tx: &TransactionData) -> TxId {
+        let bytes = bincode::serialize(tx).unwrap_or_default();
+        Sha256::digest(&bytes).into()
+    }
+
+    /// Effective ordering priority: the fee scaled by the configured
+    /// multiplier for the transaction's payload type.
+    fn effective_priority(&self, tx: &TransactionData) -> f64 {
+        tx.fee as f64 * self.config.type_priority.multiplier(tx.payload_type())
+    }
+
+    /// Admits a transaction, enforcing the fee floor and evicting the
+    /// lowest-priority entry when full.
+    pub fn submit(&self, tx: TransactionData) -> Result<TxId, DAGError> {
+        if tx.fee < self.config.min_tx_fee {
+            return Err(DAGError::InsufficientFee(format!(
+                "fee {} below minimum {}",
+                tx.fee, self.config.min_tx_fee
+            )));
+        }
+        let id = Self::tx_id(&tx);
+        let mut entries = self.entries.write().unwrap();
+        if entries.contains_key(&id) {
+            return Err(DAGError::ValidationError(
+                "transaction already in mempool".into(),
+            ));
+        }
+        if entries.len() >= self.config.max_size {
+            let lowest = entries
+                .iter()
+                .map(|(id, e)| (*id, self.effective_priority(&e.tx)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            match lowest {
+                Some((lowest_id, lowest_priority))
+                    if lowest_priority < self.effective_priority(&tx) =>
+                {
+                    entries.remove(&lowest_id);
+                }
+                _ => {
+                    return Err(DAGError::ValidationError("mempool full".into()));
+                }
+            }
+        }
+        let fee = tx.fee;
+        entries.insert(
+            id,
+            MempoolEntry {
+                tx,
+                fee,
+                added_at: Instant::now(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Drops entries that have waited longer than the TTL, regardless of
+    /// mempool size, and returns how many were removed. A transaction that
+    /// never becomes valid would otherwise sit until size-based eviction.
+    pub fn evict_expired(&self) -> usize {
+        if self.config.tx_ttl_secs == 0 {
+            return 0;
+        }
+        let ttl = std::time::Duration::from_secs(self.config.tx_ttl_secs);
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        entries.retain(|_, e| e.added_at.elapsed() < ttl);
+        let expired = before - entries.len();
+        *self.expired_total.write().unwrap() += expired as u64;
+        expired
+    }
+
+    /// Total entries dropped by TTL expiry since startup.
+    pub fn expired_total(&self) -> u64 {
+        *self.expired_total.read().unwrap()
+    }
+
+    /// Removes and returns up to `n` entries, highest effective priority
+    /// (fee times payload-type multiplier) first.
+    pub fn take_batch(&self, n: usize) -> Vec<MempoolEntry> {
+        let mut entries = self.entries.write().unwrap();
+        let mut sorted: Vec<(TxId, f64)> = entries
+            .iter()
+            .map(|(id, e)| (*id, self.effective_priority(&e.tx)))
+            .collect();
+        sorted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        sorted
+            .into_iter()
+            .take(n)
+            .filter_map(|(id, _)| entries.remove(&id))
+            .collect()
+    }
+
+    /// Re-queues an entry whose validation failed transiently.
+    pub fn requeue(&self, entry: MempoolEntry) {
+        let id = Self::tx_id(&entry.tx);
+        self.entries.write().unwrap().entry(id).or_insert(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().unwrap().is_empty()
+    }
+
+    /// Sum of fees of all queued transactions.
+    pub fn total_fees(&self) -> u64 {
+        self.entries.read().unwrap().values().map(|e| e.fee).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx_with_fee(nonce: u64, fee: u64) -> TransactionData {
+        TransactionData {
+            source: "alice".into(),
+            target: "bob".into(),
+            amount: 100,
+            currency: 1,
+            nonce,
+            fee,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn fee_floor_enforced() {
+        let mempool = Mempool::new(MempoolConfig {
+            max_size: 10,
+            min_tx_fee: 1_000,
+            ..MempoolConfig::default()
+        });
+        assert!(matches!(
+            mempool.submit(tx_with_fee(1, 999)),
+            Err(DAGError::InsufficientFee(_))
+        ));
+        assert!(mempool.submit(tx_with_fee(1, 1_000)).is_ok());
+    }
+
+    #[test]
+    fn batch_is_fee_ordered() {
+        let mempool = Mempool::new(MempoolConfig::default());
+        mempool.submit(tx_with_fee(1, 1_000)).unwrap();
+        mempool.submit(tx_with_fee(2, 5_000)).unwrap();
+        mempool.submit(tx_with_fee(3, 2_000)).unwrap();
+        let batch = mempool.take_batch(2);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].fee, 5_000);
+        assert_eq!(batch[1].fee, 2_000);
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn type_multiplier_lets_equal_fee_cns_jump_the_queue() {
+        use crate::vertex::{CnsOp, Payload};
+
+        let mempool = Mempool::new(MempoolConfig {
+            type_priority: TypePriority {
+                cns: 2.0,
+                ..TypePriority::default()
+            },
+            ..MempoolConfig::default()
+        });
+        mempool.submit(tx_with_fee(1, 1_000)).unwrap();
+        let mut cns = tx_with_fee(2, 1_000);
+        cns.user_data = Payload::Cns(CnsOp {
+            op: "reg".into(),
+            name: "alice.cs".into(),
+        })
+        .to_user_data();
+        mempool.submit(cns).unwrap();
+
+        // Equal fees, but the CNS registration's 2x multiplier wins.
+        let batch = mempool.take_batch(2);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].tx.payload_type(), PayloadType::Cns);
+        assert_eq!(batch[1].tx.payload_type(), PayloadType::Transfer);
+    }
+
+    #[test]
+    fn expired_entries_are_evicted_and_counted() {
+        let mempool = Mempool::new(MempoolConfig {
+            max_size: 10,
+            min_tx_fee: 1,
+            tx_ttl_secs: 60,
+            ..MempoolConfig::default()
+        });
+        mempool.submit(tx_with_fee(1, 10)).unwrap();
+        // A fresh entry survives an eviction pass.
+        assert_eq!(mempool.evict_expired(), 0);
+        assert_eq!(mempool.len(), 1);
+
+        // Backdate an entry past the TTL via the requeue path.
+        let stale = MempoolEntry {
+            tx: tx_with_fee(2, 10),
+            fee: 10,
+            added_at: Instant::now() - std::time::Duration::from_secs(61),
+        };
+        mempool.requeue(stale);
+        assert_eq!(mempool.len(), 2);
+        assert_eq!(mempool.evict_expired(), 1);
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(mempool.expired_total(), 1);
+    }
+
+    #[test]
+    fn full_mempool_evicts_lowest_fee() {
+        let mempool = Mempool::new(MempoolConfig {
+            max_size: 2,
+            min_tx_fee: 1,
+            ..MempoolConfig::default()
+        });
+        mempool.submit(tx_with_fee(1, 10)).unwrap();
+        mempool.submit(tx_with_fee(2, 20)).unwrap();
+        mempool.submit(tx_with_fee(3, 30)).unwrap();
+        assert_eq!(mempool.len(), 2);
+        let batch = mempool.take_batch(2);
+        assert!(batch.iter().all(|e| e.fee >= 20));
+    }
+}
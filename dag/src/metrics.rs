@@ -0,0 +1,147 @@
+//! Node-level metrics, refreshed periodically by the metrics task.
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bounds (ms) of the finality latency histogram buckets; one more
+/// unbounded bucket catches everything slower.
+pub const LATENCY_BUCKET_BOUNDS_MS: [u64; 9] = [1, 5, 10, 50, 100, 500, 1_000, 5_000, 10_000];
+
+/// Histogram of insertion-to-finality latencies with a running average.
+#[derive(Debug, Default)]
+pub struct FinalityLatencyTracker {
+    /// One count per bound plus the overflow bucket.
+    counts: [u64; LATENCY_BUCKET_BOUNDS_MS.len() + 1],
+    total: u64,
+    sum_ms: u64,
+    max_ms: u64,
+}
+
+impl FinalityLatencyTracker {
+    /// Records one finality latency.
+    pub fn record(&mut self, latency_ms: u64) {
+        let bucket = LATENCY_BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(LATENCY_BUCKET_BOUNDS_MS.len());
+        self.counts[bucket] += 1;
+        self.total += 1;
+        self.sum_ms += latency_ms;
+        self.max_ms = self.max_ms.max(latency_ms);
+    }
+
+    /// Samples recorded so far.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Running average latency, 0 when nothing was recorded.
+    pub fn average_ms(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.sum_ms as f64 / self.total as f64
+    }
+
+    /// Latency at quantile `q` (0..=1), reported as the upper bound of the
+    /// bucket the quantile falls in; the overflow bucket reports the maximum
+    /// observed latency.
+    pub fn percentile_ms(&self, q: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+        let rank = (q * self.total as f64).ceil() as u64;
+        let mut cumulative = 0u64;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= rank {
+                return LATENCY_BUCKET_BOUNDS_MS
+                    .get(bucket)
+                    .copied()
+                    .unwrap_or(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+
+    /// Bucket upper bounds paired with their counts; the final entry is the
+    /// unbounded overflow bucket, reported with `u64::MAX`.
+    pub fn buckets(&self) -> Vec<(u64, u64)> {
+        LATENCY_BUCKET_BOUNDS_MS
+            .iter()
+            .copied()
+            .chain(std::iter::once(u64::MAX))
+            .zip(self.counts.iter().copied())
+            .collect()
+    }
+}
+
+/// Snapshot of node health and throughput.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeMetrics {
+    /// Total vertices in storage.
+    pub vertices_total: u64,
+    /// Vertices finalized so far.
+    pub vertices_finalized: u64,
+    /// Currently connected peers.
+    pub peer_count: usize,
+    /// Transactions waiting in the mempool.
+    pub mempool_size: usize,
+    /// Mempool entries dropped because they outlived the TTL.
+    pub mempool_expired: u64,
+    /// Average insertion-to-finality latency in milliseconds.
+    pub average_finality_ms: f64,
+    /// Finality latency percentiles, as histogram bucket upper bounds.
+    pub finality_p50_ms: u64,
+    pub finality_p95_ms: u64,
+    pub finality_p99_ms: u64,
+    /// Seconds since the node started.
+    pub uptime_secs: u64,
+    /// Current consensus round.
+    pub consensus_round: u64,
+    /// Supervised background tasks that died from a panic.
+    pub task_panics: u64,
+    /// Conflicting finalized spends: consensus safety violations.
+    pub safety_violations: u64,
+    /// Events missed by lagging engine-event subscribers.
+    pub events_lagged: u64,
+    /// Finality webhook notifications that could not be delivered.
+    pub webhook_failures: u64,
+    /// Scheduled storage compactions completed.
+    pub compactions_run: u64,
+    /// Wall-clock time (ms since the epoch) of the last scheduled snapshot;
+    /// 0 when none was taken yet.
+    pub last_snapshot_at_ms: u64,
+    /// Size in bytes of the last scheduled snapshot.
+    pub last_snapshot_bytes: u64,
+    /// Total fees destroyed under the burn policy.
+    pub fees_burned: u64,
+    /// Total fees paid out under the reward policy.
+    pub fees_collected: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_tracker_reports_average_and_percentiles() {
+        let mut tracker = FinalityLatencyTracker::default();
+        // 90 fast samples and 10 slow ones.
+        for _ in 0..90 {
+            tracker.record(4);
+        }
+        for _ in 0..10 {
+            tracker.record(400);
+        }
+        assert_eq!(tracker.total(), 100);
+        let expected_avg = (90.0 * 4.0 + 10.0 * 400.0) / 100.0;
+        assert!((tracker.average_ms() - expected_avg).abs() < f64::EPSILON);
+        // p50 falls in the <=5ms bucket, p95 and p99 in the <=500ms bucket.
+        assert_eq!(tracker.percentile_ms(0.5), 5);
+        assert_eq!(tracker.percentile_ms(0.95), 500);
+        assert_eq!(tracker.percentile_ms(0.99), 500);
+        let populated: u64 = tracker.buckets().iter().map(|(_, count)| count).sum();
+        assert_eq!(populated, 100);
+    }
+}
+
@@ -0,0 +1,1678 @@
+//! Peer-to-peer networking: framed TCP transport, handshake, gossip and sync.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, RwLock};
+
+use crate::consensus::VirtualVote;
+use crate::engine::DAGEngine;
+use crate::error::DAGError;
+use crate::identity::NodeIdentity;
+use crate::state::COINBASE_SOURCE;
+use crate::storage::Cursor;
+use crate::vertex::{DAGVertex, PayloadType, VertexHash};
+
+/// Maximum accepted frame size (16 MiB).
+pub const MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Advertised protocol version string.
+pub const PROTOCOL_VERSION: &str = "1.0.0";
+
+/// Lowest handshake major version we interoperate with.
+const MIN_PROTOCOL_MAJOR: u64 = 1;
+
+/// Highest handshake major version we interoperate with.
+const MAX_PROTOCOL_MAJOR: u64 = 1;
+
+/// Interval between keepalive pings.
+const PING_INTERVAL_SECS: u64 = 30;
+
+/// Peers silent for longer than this are swept.
+const PEER_TIMEOUT_SECS: u64 = 120;
+
+/// Vertices per sync response page.
+const SYNC_PAGE_SIZE: usize = 256;
+
+/// Most addresses kept in the persisted address book.
+const ADDRESS_BOOK_CAP: usize = 64;
+
+/// Dial failures after which an address is dropped from the book.
+const ADDRESS_BOOK_MAX_FAILURES: u32 = 3;
+
+/// Undecodable frames tolerated from a peer before it is disconnected.
+const MAX_DECODE_FAILURES: u32 = 5;
+
+/// Most messages buffered for a disconnected peer awaiting reconnection.
+const RECONNECT_QUEUE_CAP: usize = 256;
+
+/// How long messages are held for a disconnected peer before being dropped.
+const RECONNECT_WINDOW_SECS: u64 = 30;
+
+/// Weight of a new round-trip sample in the per-peer latency EWMA.
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+/// The numeric major component of an advertised version string.
+fn protocol_major(version: &str) -> Option<u64> {
+    version.split('.').next()?.parse().ok()
+}
+
+/// Whether a peer's advertised version falls in the supported major range.
+/// Unparseable versions are unsupported.
+fn version_supported(version: &str) -> bool {
+    matches!(
+        protocol_major(version),
+        Some(major) if (MIN_PROTOCOL_MAJOR..=MAX_PROTOCOL_MAJOR).contains(&major)
+    )
+}
+
+/// Structured network error type: dial/accept problems, undecodable wire
+/// bytes and well-formed-but-wrong messages are distinguishable, so callers
+/// and peer scoring can react to the cause rather than a string.
+#[derive(Debug, Error)]
+pub enum NetworkError {
+    #[error("connection failed: {0}")]
+    ConnectionFailed(String),
+    #[error("serialization error: {0}")]
+    SerializationError(String),
+    #[error("invalid message: {0}")]
+    InvalidMessage(String),
+}
+
+impl From<NetworkError> for DAGError {
+    fn from(e: NetworkError) -> Self {
+        match e {
+            NetworkError::SerializationError(msg) => DAGError::SerializationError(msg),
+            other => DAGError::NetworkError(other.to_string()),
+        }
+    }
+}
+
+/// Decodes one wire frame into a message, surfacing failures as
+/// [`NetworkError::SerializationError`].
+fn decode_message(buf: &[u8]) -> Result<NetworkMessage, NetworkError> {
+    bincode::deserialize(buf).map_err(|e| NetworkError::SerializationError(e.to_string()))
+}
+
+/// Wire messages exchanged between peers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NetworkMessage {
+    Handshake {
+        node_id: String,
+        version: String,
+        listen_port: u16,
+        /// The dialer's Ed25519 identity public key.
+        public_key: Vec<u8>,
+        /// Fresh nonce the responder must sign back.
+        nonce: u64,
+        /// Signature over `nonce` with the identity key.
+        signature: Vec<u8>,
+    },
+    HandshakeResponse {
+        accepted: bool,
+        node_id: String,
+        /// The responder's Ed25519 identity public key.
+        public_key: Vec<u8>,
+        /// Signature over the dialer's nonce with the identity key.
+        signature: Vec<u8>,
+    },
+    Ping {
+        nonce: u64,
+    },
+    Pong {
+        nonce: u64,
+    },
+    NewVertex(Box<DAGVertex>),
+    ConsensusVote(VirtualVote),
+    PeerShare(Vec<SocketAddr>),
+    SyncRequest {
+        from_clock: u64,
+        to_clock: u64,
+        /// Resume after this cursor; `None` starts from `from_clock`.
+        after: Option<Cursor>,
+    },
+    SyncResponse {
+        vertices: Vec<DAGVertex>,
+        to_clock: u64,
+        /// Cursor for the next page, `None` when the range is exhausted.
+        next: Option<Cursor>,
+    },
+}
+
+/// Book-keeping for one connected peer.
+pub struct PeerInfo {
+    pub node_id: String,
+    pub addr: SocketAddr,
+    pub last_seen: Instant,
+    pub score: i32,
+    pub connected: bool,
+    pub sender: mpsc::UnboundedSender<NetworkMessage>,
+    /// EWMA of measured ping round-trips; `None` until the first sample.
+    pub latency_ms: Option<f64>,
+    /// Nonce and send time of the ping awaiting its pong.
+    pending_ping: Option<(u64, Instant)>,
+}
+
+/// Most vertex hashes remembered by the broadcast-suppression cache.
+const SEEN_BROADCAST_CAP: usize = 4_096;
+/// Seconds a broadcast vertex stays suppressed from being broadcast again.
+const SEEN_BROADCAST_TTL_SECS: u64 = 60;
+
+/// Outbound messages buffered for a recently disconnected peer, flushed in
+/// order if the peer reconnects within [`RECONNECT_WINDOW_SECS`].
+struct ParkedPeer {
+    messages: VecDeque<NetworkMessage>,
+    since: Instant,
+}
+
+impl ParkedPeer {
+    fn expired(&self) -> bool {
+        self.since.elapsed().as_secs() >= RECONNECT_WINDOW_SECS
+    }
+
+    /// Buffers a message, dropping the oldest once the queue is full.
+    fn push(&mut self, msg: NetworkMessage) {
+        if self.messages.len() >= RECONNECT_QUEUE_CAP {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(msg);
+    }
+}
+
+/// Serializable peer summary for APIs and the CLI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerSummary {
+    pub node_id: String,
+    pub addr: SocketAddr,
+    pub score: i32,
+    pub last_seen_secs: u64,
+    pub connected: bool,
+    /// Smoothed ping round-trip, `None` when not yet measured.
+    pub latency_ms: Option<f64>,
+}
+
+/// Edge filter applied to gossiped vertices before they are inserted and
+/// relayed. Distinct from consensus validation: a non-staked node enforces
+/// it to stop obvious spam from propagating through it, not to judge
+/// finality.
+#[derive(Debug, Clone)]
+pub struct RelayPolicy {
+    /// Smallest fee a relayed transaction may carry; coinbase reward
+    /// vertices are exempt, carrying no fee by design.
+    pub min_relay_fee: u64,
+    /// Drop vertices whose `user_data` parses as raw, unrecognized bytes.
+    pub reject_raw_payloads: bool,
+}
+
+impl RelayPolicy {
+    /// Whether a gossiped vertex passes the filter.
+    fn accepts(&self, vertex: &DAGVertex) -> bool {
+        let tx = &vertex.transaction_data;
+        if tx.source != COINBASE_SOURCE && tx.fee < self.min_relay_fee {
+            return false;
+        }
+        if self.reject_raw_payloads && tx.payload_type() == PayloadType::Raw {
+            return false;
+        }
+        true
+    }
+}
+
+/// Network configuration.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// Address the p2p listener binds; 0.0.0.0 for all interfaces.
+    pub listen_addr: IpAddr,
+    pub port: u16,
+    pub bootstrap_peers: Vec<SocketAddr>,
+    /// Most peers kept connected at once.
+    pub max_connections: usize,
+    /// Where the persisted address book lives; disabled when unset.
+    pub address_book_path: Option<PathBuf>,
+    /// TCP keepalive probe time on peer sockets, in seconds; 0 disables.
+    pub keepalive_secs: u64,
+    /// How long a peer may stay completely silent before its connection is
+    /// dropped, in seconds; 0 falls back to the periodic `last_seen` sweep.
+    pub read_timeout_secs: u64,
+    /// Acceptance policy for gossiped vertices; `None` relays anything
+    /// well-formed.
+    pub relay_policy: Option<RelayPolicy>,
+}
+
+/// Manages the listener, peer set and message dispatch.
+pub struct NetworkManager {
+    identity: Arc<NodeIdentity>,
+    node_id: String,
+    config: NetworkConfig,
+    engine: Arc<DAGEngine>,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    /// Dialable addresses and their consecutive failure counts.
+    address_book: Arc<RwLock<HashMap<SocketAddr, u32>>>,
+    /// Message queues for peers in their reconnect window.
+    parked: Arc<RwLock<HashMap<String, ParkedPeer>>>,
+    /// Vertices broadcast within the last [`SEEN_BROADCAST_TTL_SECS`];
+    /// broadcasting them again is suppressed to stop gossip loops.
+    seen_broadcast: Arc<RwLock<HashMap<VertexHash, Instant>>>,
+    actual_port: AtomicU16,
+}
+
+async fn write_frame(writer: &mut OwnedWriteHalf, msg: &NetworkMessage) -> Result<(), DAGError> {
+    let bytes = bincode::serialize(msg)?;
+    let len = bytes.len() as u32;
+    if len > MAX_FRAME_SIZE {
+        return Err(NetworkError::SerializationError("frame too large".into()).into());
+    }
+    writer.write_all(&len.to_le_bytes()).await?;
+    writer.write_all(&bytes).await?;
+    Ok(())
+}
+
+async fn read_frame(reader: &mut OwnedReadHalf) -> Result<Vec<u8>, DAGError> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf).await?;
+    let len = u32::from_le_bytes(len_buf);
+    if len > MAX_FRAME_SIZE {
+        return Err(NetworkError::InvalidMessage(format!(
+            "frame length {len} exceeds maximum"
+        ))
+        .into());
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+impl NetworkManager {
+    pub fn new(
+        identity: Arc<NodeIdentity>,
+        config: NetworkConfig,
+        engine: Arc<DAGEngine>,
+    ) -> Self {
+        let address_book = config
+            .address_book_path
+            .as_deref()
+            .map(load_address_book)
+            .unwrap_or_default();
+        NetworkManager {
+            node_id: identity.node_id().to_string(),
+            identity,
+            config,
+            engine,
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            address_book: Arc::new(RwLock::new(address_book)),
+            parked: Arc::new(RwLock::new(HashMap::new())),
+            seen_broadcast: Arc::new(RwLock::new(HashMap::new())),
+            actual_port: AtomicU16::new(0),
+        }
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Port the listener actually bound (useful when configured with 0).
+    pub fn local_port(&self) -> u16 {
+        self.actual_port.load(Ordering::SeqCst)
+    }
+
+    /// Address the listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.config.listen_addr, self.local_port())
+    }
+
+    /// Starts the listener, maintenance loop and bootstrap dials.
+    pub async fn start(self: &Arc<Self>) -> Result<(), DAGError> {
+        self.clone().start_listener().await?;
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.maintenance_loop().await;
+        });
+        for addr in self.config.bootstrap_peers.clone() {
+            let manager = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = manager.connect_to_peer(addr).await {
+                    warn!("bootstrap connect to {addr} failed: {e}");
+                }
+            });
+        }
+        // Seed reconnects from the persisted address book as well.
+        let remembered: Vec<SocketAddr> = self
+            .address_book
+            .read()
+            .await
+            .keys()
+            .filter(|addr| !self.config.bootstrap_peers.contains(addr))
+            .copied()
+            .collect();
+        for addr in remembered {
+            let manager = self.clone();
+            tokio::spawn(async move {
+                manager.connect_to_new_peer(addr).await;
+            });
+        }
+        Ok(())
+    }
+
+    async fn start_listener(self: Arc<Self>) -> Result<(), DAGError> {
+        let bind_addr = SocketAddr::new(self.config.listen_addr, self.config.port);
+        let listener = TcpListener::bind(bind_addr)
+            .await
+            .map_err(|e| NetworkError::ConnectionFailed(format!("bind to {bind_addr} failed: {e}")))?;
+        let port = listener
+            .local_addr()
+            .map(|a| a.port())
+            .unwrap_or(self.config.port);
+        self.actual_port.store(port, Ordering::SeqCst);
+        info!("p2p listener on {}:{port}", self.config.listen_addr);
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => {
+                        let manager = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = manager.handle_peer_connection(stream, addr).await {
+                                debug!("peer {addr} disconnected: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        warn!("accept failed: {e}");
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Enables TCP keepalive on a peer socket so the OS notices silently
+    /// dropped links (NAT timeout, cable pull) instead of holding a phantom
+    /// connection open indefinitely.
+    fn apply_keepalive(&self, stream: &TcpStream) {
+        if self.config.keepalive_secs == 0 {
+            return;
+        }
+        let keepalive = socket2::TcpKeepalive::new()
+            .with_time(Duration::from_secs(self.config.keepalive_secs))
+            .with_interval(Duration::from_secs(self.config.keepalive_secs));
+        if let Err(e) = socket2::SockRef::from(stream).set_tcp_keepalive(&keepalive) {
+            warn!("failed to set tcp keepalive: {e}");
+        }
+    }
+
+    /// Handles one inbound connection end-to-end.
+    async fn handle_peer_connection(
+        self: Arc<Self>,
+        stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Result<(), DAGError> {
+        self.apply_keepalive(&stream);
+        let (mut reader, mut writer) = stream.into_split();
+
+        // First frame must be a handshake proving possession of the
+        // identity key the claimed node id was derived from.
+        let buf = read_frame(&mut reader).await?;
+        let (peer_id, nonce, version) = match decode_message(&buf) {
+            Ok(NetworkMessage::Handshake {
+                node_id,
+                version,
+                public_key,
+                nonce,
+                signature,
+                ..
+            }) => {
+                if NodeIdentity::derive_node_id(&public_key) != node_id
+                    || !NodeIdentity::verify_nonce(&public_key, nonce, &signature)
+                {
+                    return Err(NetworkError::InvalidMessage(format!(
+                        "handshake identity check failed for {addr}"
+                    ))
+                    .into());
+                }
+                (node_id, nonce, version)
+            }
+            _ => {
+                return Err(NetworkError::InvalidMessage("expected handshake".into()).into());
+            }
+        };
+        if !version_supported(&version) {
+            info!(
+                "rejecting peer {addr}: protocol version {version} outside supported majors \
+                 {MIN_PROTOCOL_MAJOR}..={MAX_PROTOCOL_MAJOR}"
+            );
+            write_frame(&mut writer, &self.handshake_response(false, nonce)).await?;
+            return Ok(());
+        }
+        if peer_id == self.node_id {
+            debug!("dropping inbound self-connection from {addr}");
+            write_frame(
+                &mut writer,
+                &self.handshake_response(false, nonce),
+            )
+            .await?;
+            return Ok(());
+        }
+        let accepted = self.make_room_for_peer().await;
+        write_frame(&mut writer, &self.handshake_response(accepted, nonce)).await?;
+        if !accepted {
+            info!("rejecting inbound peer {addr}: at max_connections");
+            return Ok(());
+        }
+
+        let rx = self.register_peer(peer_id.clone(), addr).await;
+        Self::spawn_writer(writer, rx);
+        self.read_loop(&mut reader, &peer_id).await;
+        self.park_peer(&peer_id).await;
+        Ok(())
+    }
+
+    /// Dials a peer, performs the handshake and registers it.
+    pub async fn connect_to_peer(self: &Arc<Self>, addr: SocketAddr) -> Result<(), DAGError> {
+        let stream = match TcpStream::connect(addr).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.note_peer_failure(addr).await;
+                return Err(NetworkError::ConnectionFailed(format!(
+                    "connect to {addr} failed: {e}"
+                ))
+                .into());
+            }
+        };
+        self.apply_keepalive(&stream);
+        let (mut reader, mut writer) = stream.into_split();
+        let nonce: u64 = rand::random();
+        write_frame(
+            &mut writer,
+            &NetworkMessage::Handshake {
+                node_id: self.node_id.clone(),
+                version: PROTOCOL_VERSION.to_string(),
+                listen_port: self.local_port(),
+                public_key: self.identity.public_key_bytes().to_vec(),
+                nonce,
+                signature: self.identity.sign_nonce(nonce),
+            },
+        )
+        .await?;
+        let buf = read_frame(&mut reader).await?;
+        let peer_id = match decode_message(&buf) {
+            Ok(NetworkMessage::HandshakeResponse {
+                accepted,
+                node_id,
+                public_key,
+                signature,
+            }) if accepted => {
+                if NodeIdentity::derive_node_id(&public_key) != node_id
+                    || !NodeIdentity::verify_nonce(&public_key, nonce, &signature)
+                {
+                    return Err(NetworkError::InvalidMessage(format!(
+                        "handshake identity check failed for {addr}"
+                    ))
+                    .into());
+                }
+                node_id
+            }
+            Ok(NetworkMessage::HandshakeResponse { .. }) => {
+                return Err(NetworkError::ConnectionFailed("handshake rejected".into()).into());
+            }
+            _ => {
+                return Err(NetworkError::InvalidMessage("bad handshake response".into()).into());
+            }
+        };
+        if peer_id == self.node_id {
+            // Our own address leaked back to us; forget it.
+            self.address_book.write().await.remove(&addr);
+            return Err(NetworkError::ConnectionFailed(format!(
+                "refusing self-connection to {addr}"
+            ))
+            .into());
+        }
+
+        let rx = self.register_peer(peer_id.clone(), addr).await;
+        Self::spawn_writer(writer, rx);
+        self.remember_peer(addr).await;
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.read_loop(&mut reader, &peer_id).await;
+            manager.park_peer(&peer_id).await;
+        });
+        Ok(())
+    }
+
+    /// Builds a handshake response signing the dialer's nonce with our
+    /// identity key.
+    fn handshake_response(&self, accepted: bool, nonce: u64) -> NetworkMessage {
+        NetworkMessage::HandshakeResponse {
+            accepted,
+            node_id: self.node_id.clone(),
+            public_key: self.identity.public_key_bytes().to_vec(),
+            signature: self.identity.sign_nonce(nonce),
+        }
+    }
+
+    /// Whether an address points at our own listener.
+    fn is_own_address(&self, addr: &SocketAddr) -> bool {
+        addr.port() == self.local_port()
+            && (addr.ip().is_loopback() || addr.ip().is_unspecified())
+    }
+
+    /// Checks the connection budget for a new inbound peer, evicting the
+    /// lowest-scoring misbehaving peer to make room when possible. Returns
+    /// whether the new peer may be admitted.
+    async fn make_room_for_peer(&self) -> bool {
+        let mut peers = self.peers.write().await;
+        if peers.len() < self.config.max_connections {
+            return true;
+        }
+        // A peer scoring below zero has behaved worse than an unknown one.
+        let worst = peers
+            .iter()
+            .min_by_key(|(_, p)| p.score)
+            .map(|(id, p)| (id.clone(), p.score));
+        if let Some((id, score)) = worst {
+            if score < 0 {
+                info!("evicting peer {id} (score {score}) to make room");
+                peers.remove(&id);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Records a successfully dialed address in the address book.
+    async fn remember_peer(&self, addr: SocketAddr) {
+        let mut book = self.address_book.write().await;
+        book.insert(addr, 0);
+        while book.len() > ADDRESS_BOOK_CAP {
+            // Evict the most-failed address first.
+            if let Some(worst) = book.iter().max_by_key(|(_, f)| **f).map(|(a, _)| *a) {
+                book.remove(&worst);
+            }
+        }
+    }
+
+    /// Counts a dial failure, pruning addresses that keep failing.
+    async fn note_peer_failure(&self, addr: SocketAddr) {
+        let mut book = self.address_book.write().await;
+        if let Some(failures) = book.get_mut(&addr) {
+            *failures += 1;
+            if *failures >= ADDRESS_BOOK_MAX_FAILURES {
+                info!("dropping {addr} from address book after {failures} failures");
+                book.remove(&addr);
+            }
+        }
+    }
+
+    /// Writes the address book to its configured path.
+    pub async fn save_address_book(&self) {
+        let Some(path) = &self.config.address_book_path else {
+            return;
+        };
+        let addrs: Vec<SocketAddr> = self.address_book.read().await.keys().copied().collect();
+        match serde_json::to_vec_pretty(&addrs) {
+            Ok(bytes) => {
+                if let Err(e) = std::fs::write(path, bytes) {
+                    warn!("failed to persist address book: {e}");
+                }
+            }
+            Err(e) => warn!("failed to serialize address book: {e}"),
+        }
+    }
+
+    /// Dials an address discovered via peer share if not already connected.
+    pub async fn connect_to_new_peer(self: &Arc<Self>, addr: SocketAddr) {
+        if self.is_own_address(&addr) {
+            debug!("ignoring own address {addr} from peer discovery");
+            return;
+        }
+        let already = self.peers.read().await.values().any(|p| p.addr == addr);
+        if already {
+            return;
+        }
+        if let Err(e) = self.connect_to_peer(addr).await {
+            debug!("discovery connect to {addr} failed: {e}");
+        }
+    }
+
+    async fn register_peer(
+        &self,
+        peer_id: String,
+        addr: SocketAddr,
+    ) -> mpsc::UnboundedReceiver<NetworkMessage> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let info = PeerInfo {
+            node_id: peer_id.clone(),
+            addr,
+            last_seen: Instant::now(),
+            score: 0,
+            connected: true,
+            sender: tx.clone(),
+            latency_ms: None,
+            pending_ping: None,
+        };
+        self.peers.write().await.insert(peer_id.clone(), info);
+        if let Some(parked) = self.parked.write().await.remove(&peer_id) {
+            if !parked.expired() && !parked.messages.is_empty() {
+                info!(
+                    "flushing {} messages queued while peer {peer_id} was away",
+                    parked.messages.len()
+                );
+                for msg in parked.messages {
+                    let _ = tx.send(msg);
+                }
+            }
+        }
+        rx
+    }
+
+    /// Drops a disconnected peer but keeps an outbound queue for it, so
+    /// messages sent during a brief drop survive until it reconnects.
+    async fn park_peer(&self, peer_id: &str) {
+        if self.peers.write().await.remove(peer_id).is_some() {
+            self.parked.write().await.insert(
+                peer_id.to_string(),
+                ParkedPeer {
+                    messages: VecDeque::new(),
+                    since: Instant::now(),
+                },
+            );
+        }
+    }
+
+    fn spawn_writer(mut writer: OwnedWriteHalf, mut rx: mpsc::UnboundedReceiver<NetworkMessage>) {
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if write_frame(&mut writer, &msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    async fn read_loop(self: &Arc<Self>, reader: &mut OwnedReadHalf, peer_id: &str) {
+        let mut decode_failures = 0u32;
+        loop {
+            // Even a healthy peer pings well within the read timeout, so a
+            // stream with nothing to read for that long is dead; drop it
+            // rather than wait for the last_seen sweep.
+            let buf = if self.config.read_timeout_secs > 0 {
+                let deadline = Duration::from_secs(self.config.read_timeout_secs);
+                match tokio::time::timeout(deadline, read_frame(reader)).await {
+                    Ok(Ok(buf)) => buf,
+                    Ok(Err(_)) => break,
+                    Err(_) => {
+                        info!(
+                            "peer {peer_id} silent for {}s, disconnecting",
+                            self.config.read_timeout_secs
+                        );
+                        break;
+                    }
+                }
+            } else {
+                match read_frame(reader).await {
+                    Ok(buf) => buf,
+                    Err(_) => break,
+                }
+            };
+            match decode_message(&buf) {
+                Ok(msg) => self.process_message(msg, peer_id).await,
+                Err(e) => {
+                    // Framing is intact, so one undecodable message does not
+                    // desynchronize the stream; penalize and keep reading.
+                    decode_failures += 1;
+                    warn!(
+                        "undecodable {}-byte frame from peer {peer_id}: {e}",
+                        buf.len()
+                    );
+                    self.adjust_peer_score(peer_id, -5).await;
+                    if decode_failures >= MAX_DECODE_FAILURES {
+                        warn!("disconnecting peer {peer_id} after {decode_failures} undecodable frames");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Dispatches one received message.
+    ///
+    /// Boxed to break the `read_loop` -> `process_message` ->
+    /// `connect_to_peer` -> `read_loop` future cycle.
+    fn process_message<'a>(
+        self: &'a Arc<Self>,
+        msg: NetworkMessage,
+        peer_id: &'a str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'a>> {
+        Box::pin(self.process_message_inner(msg, peer_id))
+    }
+
+    async fn process_message_inner(self: &Arc<Self>, msg: NetworkMessage, peer_id: &str) {
+        self.touch_peer(peer_id).await;
+        match msg {
+            NetworkMessage::Ping { nonce } => {
+                // A ping from a peer that is also a registered validator
+                // doubles as a liveness heartbeat.
+                let consensus = self.engine.consensus().clone();
+                consensus.write().unwrap().record_heartbeat(peer_id);
+                self.send_to_peer(peer_id, NetworkMessage::Pong { nonce }).await;
+            }
+            NetworkMessage::Pong { nonce } => {
+                if let Some(peer) = self.peers.write().await.get_mut(peer_id) {
+                    match peer.pending_ping.take() {
+                        Some((expected, sent)) if expected == nonce => {
+                            let sample = sent.elapsed().as_secs_f64() * 1_000.0;
+                            peer.latency_ms = Some(match peer.latency_ms {
+                                Some(prev) => prev + LATENCY_EWMA_ALPHA * (sample - prev),
+                                None => sample,
+                            });
+                        }
+                        other => peer.pending_ping = other,
+                    }
+                }
+            }
+            NetworkMessage::NewVertex(vertex) => {
+                if let Some(policy) = &self.config.relay_policy {
+                    if !policy.accepts(&vertex) {
+                        debug!(
+                            "relay policy dropped vertex {} from {peer_id}",
+                            hex::encode(&vertex.tx_hash[..8])
+                        );
+                        self.adjust_peer_score(peer_id, -1).await;
+                        return;
+                    }
+                }
+                let hash = vertex.tx_hash;
+                match self.engine.insert_vertex(*vertex.clone()) {
+                    Ok(()) => {
+                        debug!("gossip vertex {} from {peer_id}", hex::encode(&hash[..8]));
+                        self.broadcast_except(NetworkMessage::NewVertex(vertex), peer_id)
+                            .await;
+                    }
+                    Err(DAGError::AlreadyExists(_)) => {
+                        // Duplicate; do not re-gossip.
+                    }
+                    Err(DAGError::InvalidVertex(e)) => {
+                        debug!("invalid vertex from {peer_id}: {e}");
+                        self.adjust_peer_score(peer_id, -5).await;
+                    }
+                    Err(e) => {
+                        debug!("gossip insert failed: {e}");
+                    }
+                }
+            }
+            NetworkMessage::ConsensusVote(vote) => {
+                let consensus = self.engine.consensus().clone();
+                let result = consensus.write().unwrap().submit_vote(vote);
+                if let Err(e) = result {
+                    debug!("vote from {peer_id} rejected: {e}");
+                }
+            }
+            NetworkMessage::PeerShare(addrs) => {
+                for addr in addrs {
+                    let manager = self.clone();
+                    tokio::spawn(async move {
+                        manager.connect_to_new_peer(addr).await;
+                    });
+                }
+            }
+            NetworkMessage::SyncRequest {
+                from_clock,
+                to_clock,
+                after,
+            } => {
+                if let Ok((vertices, next)) = self.engine.get_vertices_by_clock_range(
+                    from_clock,
+                    to_clock,
+                    SYNC_PAGE_SIZE,
+                    after,
+                ) {
+                    self.send_to_peer(
+                        peer_id,
+                        NetworkMessage::SyncResponse {
+                            vertices,
+                            to_clock,
+                            next,
+                        },
+                    )
+                    .await;
+                }
+            }
+            NetworkMessage::SyncResponse {
+                vertices,
+                to_clock,
+                next,
+            } => {
+                for vertex in vertices {
+                    let _ = self.engine.insert_vertex(vertex);
+                }
+                if let Some(cursor) = next {
+                    self.send_to_peer(
+                        peer_id,
+                        NetworkMessage::SyncRequest {
+                            from_clock: cursor.clock,
+                            to_clock,
+                            after: Some(cursor),
+                        },
+                    )
+                    .await;
+                }
+            }
+            NetworkMessage::Handshake { .. } | NetworkMessage::HandshakeResponse { .. } => {
+                // Handshakes after session setup are protocol violations.
+                self.adjust_peer_score(peer_id, -10).await;
+            }
+        }
+    }
+
+    async fn touch_peer(&self, peer_id: &str) {
+        if let Some(peer) = self.peers.write().await.get_mut(peer_id) {
+            peer.last_seen = Instant::now();
+        }
+    }
+
+    /// Adjusts a peer's behavior score.
+    pub async fn adjust_peer_score(&self, peer_id: &str, delta: i32) {
+        if let Some(peer) = self.peers.write().await.get_mut(peer_id) {
+            peer.score += delta;
+        }
+    }
+
+    async fn send_to_peer(&self, peer_id: &str, msg: NetworkMessage) {
+        if let Some(peer) = self.peers.read().await.get(peer_id) {
+            let _ = peer.sender.send(msg);
+            return;
+        }
+        if let Some(parked) = self.parked.write().await.get_mut(peer_id) {
+            if !parked.expired() {
+                parked.push(msg);
+            }
+        }
+    }
+
+    /// Records the intent to broadcast a vertex, answering whether the
+    /// broadcast should go ahead: false when the same vertex already went
+    /// out within [`SEEN_BROADCAST_TTL_SECS`]. Insert-dedup stops storage
+    /// duplication; this stops network amplification.
+    async fn note_broadcast(&self, hash: VertexHash) -> bool {
+        let mut seen = self.seen_broadcast.write().await;
+        if let Some(at) = seen.get(&hash) {
+            if at.elapsed().as_secs() < SEEN_BROADCAST_TTL_SECS {
+                return false;
+            }
+        }
+        if seen.len() >= SEEN_BROADCAST_CAP {
+            seen.retain(|_, at| at.elapsed().as_secs() < SEEN_BROADCAST_TTL_SECS);
+            if seen.len() >= SEEN_BROADCAST_CAP {
+                if let Some(evict) = seen.keys().next().copied() {
+                    seen.remove(&evict);
+                }
+            }
+        }
+        seen.insert(hash, Instant::now());
+        true
+    }
+
+    /// Sends a message to every connected peer, queueing it for peers in
+    /// their reconnect window. Vertex broadcasts are idempotent: a vertex
+    /// already sent within the seen TTL goes out only once.
+    pub async fn broadcast_message(&self, msg: NetworkMessage) {
+        if let NetworkMessage::NewVertex(vertex) = &msg {
+            if !self.note_broadcast(vertex.tx_hash).await {
+                return;
+            }
+        }
+        for peer in self.peers.read().await.values() {
+            let _ = peer.sender.send(msg.clone());
+        }
+        for parked in self.parked.write().await.values_mut() {
+            if !parked.expired() {
+                parked.push(msg.clone());
+            }
+        }
+    }
+
+    async fn broadcast_except(&self, msg: NetworkMessage, except: &str) {
+        if let NetworkMessage::NewVertex(vertex) = &msg {
+            if !self.note_broadcast(vertex.tx_hash).await {
+                return;
+            }
+        }
+        for (id, peer) in self.peers.read().await.iter() {
+            if id != except {
+                let _ = peer.sender.send(msg.clone());
+            }
+        }
+        for (id, parked) in self.parked.write().await.iter_mut() {
+            if id != except && !parked.expired() {
+                parked.push(msg.clone());
+            }
+        }
+    }
+
+    /// Pings every peer, recording the nonce and send time so the matching
+    /// pong yields a round-trip sample.
+    async fn ping_peers(&self) {
+        let nonce: u64 = rand::random();
+        let now = Instant::now();
+        for peer in self.peers.write().await.values_mut() {
+            peer.pending_ping = Some((nonce, now));
+            let _ = peer.sender.send(NetworkMessage::Ping { nonce });
+        }
+    }
+
+    /// The connected peer best suited for sync requests: lowest measured
+    /// round-trip latency, unmeasured peers considered last.
+    pub async fn best_sync_peer(&self) -> Option<String> {
+        self.peers
+            .read()
+            .await
+            .values()
+            .min_by(|a, b| {
+                let key = |p: &PeerInfo| p.latency_ms.unwrap_or(f64::INFINITY);
+                key(a).total_cmp(&key(b))
+            })
+            .map(|p| p.node_id.clone())
+    }
+
+    /// Requests vertices in a clock range from the lowest-latency peer.
+    pub async fn request_sync(&self, from_clock: u64, to_clock: u64) {
+        if let Some(peer_id) = self.best_sync_peer().await {
+            self.send_to_peer(
+                &peer_id,
+                NetworkMessage::SyncRequest {
+                    from_clock,
+                    to_clock,
+                    after: None,
+                },
+            )
+            .await;
+        }
+    }
+
+    pub async fn peer_count(&self) -> usize {
+        self.peers.read().await.len()
+    }
+
+    /// Snapshot of all peers for APIs and the CLI.
+    pub async fn get_peer_details(&self) -> Vec<PeerSummary> {
+        self.peers
+            .read()
+            .await
+            .values()
+            .map(|p| PeerSummary {
+                node_id: p.node_id.clone(),
+                addr: p.addr,
+                score: p.score,
+                last_seen_secs: p.last_seen.elapsed().as_secs(),
+                connected: p.connected,
+                latency_ms: p.latency_ms,
+            })
+            .collect()
+    }
+
+    async fn maintenance_loop(self: Arc<Self>) {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(PING_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            self.ping_peers().await;
+            {
+                let mut peers = self.peers.write().await;
+                peers.retain(|id, peer| {
+                    let alive = peer.last_seen.elapsed().as_secs() < PEER_TIMEOUT_SECS;
+                    if !alive {
+                        info!("sweeping silent peer {id}");
+                    }
+                    alive
+                });
+            }
+            self.parked.write().await.retain(|id, parked| {
+                let keep = !parked.expired();
+                if !keep && !parked.messages.is_empty() {
+                    debug!(
+                        "dropping {} queued messages for peer {id}: reconnect window elapsed",
+                        parked.messages.len()
+                    );
+                }
+                keep
+            });
+            self.save_address_book().await;
+        }
+    }
+}
+
+/// Loads a persisted address book, tolerating a missing or corrupt file.
+fn load_address_book(path: &std::path::Path) -> HashMap<SocketAddr, u32> {
+    let Ok(bytes) = std::fs::read(path) else {
+        return HashMap::new();
+    };
+    match serde_json::from_slice::<Vec<SocketAddr>>(&bytes) {
+        Ok(addrs) => addrs.into_iter().map(|a| (a, 0)).collect(),
+        Err(e) => {
+            warn!("ignoring corrupt address book: {e}");
+            HashMap::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::engine::DAGEngineConfig;
+    use crate::vertex::TransactionData;
+
+    fn test_manager_with_policy(
+        dir: &std::path::Path,
+        policy: RelayPolicy,
+    ) -> Arc<NetworkManager> {
+        let config = DAGEngineConfig {
+            data_dir: dir.to_path_buf(),
+            ..DAGEngineConfig::default()
+        };
+        let engine = Arc::new(DAGEngine::new(config).unwrap());
+        Arc::new(NetworkManager::new(
+            Arc::new(NodeIdentity::generate()),
+            NetworkConfig {
+                listen_addr: Ipv4Addr::LOCALHOST.into(),
+                port: 0,
+                bootstrap_peers: Vec::new(),
+                max_connections: 50,
+                address_book_path: None,
+                keepalive_secs: 30,
+                read_timeout_secs: 90,
+                relay_policy: Some(policy),
+            },
+            engine,
+        ))
+    }
+
+    fn test_manager(dir: &std::path::Path) -> Arc<NetworkManager> {
+        let config = DAGEngineConfig {
+            data_dir: dir.to_path_buf(),
+            ..DAGEngineConfig::default()
+        };
+        let engine = Arc::new(DAGEngine::new(config).unwrap());
+        Arc::new(NetworkManager::new(
+            Arc::new(NodeIdentity::generate()),
+            NetworkConfig {
+                listen_addr: Ipv4Addr::LOCALHOST.into(),
+                port: 0,
+                bootstrap_peers: Vec::new(),
+                max_connections: 50,
+                address_book_path: None,
+                keepalive_secs: 30,
+                read_timeout_secs: 90,
+                relay_policy: None,
+            },
+            engine,
+        ))
+    }
+
+    #[tokio::test]
+    async fn handshake_and_gossip() {
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let node_a = test_manager(dir_a.path());
+        let node_b = test_manager(dir_b.path());
+
+        node_a.start().await.unwrap();
+        let addr: SocketAddr = format!("127.0.0.1:{}", node_a.local_port())
+            .parse()
+            .unwrap();
+        node_b.connect_to_peer(addr).await.unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(node_a.peer_count().await, 1);
+        assert_eq!(node_b.peer_count().await, 1);
+
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: 0,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let vertex = DAGVertex::new(tx, Vec::new(), 0, 0);
+        node_b.engine.insert_vertex(vertex.clone()).unwrap();
+        node_b
+            .broadcast_message(NetworkMessage::NewVertex(Box::new(vertex.clone())))
+            .await;
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(node_a.engine.get_vertex(&vertex.tx_hash).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn messages_queued_during_a_drop_are_delivered_on_reconnect() {
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let node_a = test_manager(dir_a.path());
+        let node_b = test_manager(dir_b.path());
+
+        node_a.start().await.unwrap();
+        let addr: SocketAddr = format!("127.0.0.1:{}", node_a.local_port())
+            .parse()
+            .unwrap();
+        node_b.connect_to_peer(addr).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(node_a.peer_count().await, 1);
+
+        // The connection to b drops; a vertex broadcast during the gap
+        // lands in b's reconnect queue instead of being lost.
+        node_a.park_peer(node_b.node_id()).await;
+        assert_eq!(node_a.peer_count().await, 0);
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: 0,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let vertex = DAGVertex::new(tx, Vec::new(), 0, 0);
+        node_a.engine.insert_vertex(vertex.clone()).unwrap();
+        node_a
+            .broadcast_message(NetworkMessage::NewVertex(Box::new(vertex.clone())))
+            .await;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(node_b.engine.get_vertex(&vertex.tx_hash).unwrap().is_none());
+
+        // Reconnecting flushes the queue.
+        node_b.connect_to_peer(addr).await.unwrap();
+        let mut delivered = false;
+        for _ in 0..20 {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            if node_b.engine.get_vertex(&vertex.tx_hash).unwrap().is_some() {
+                delivered = true;
+                break;
+            }
+        }
+        assert!(delivered, "queued message never reached the reconnected peer");
+    }
+
+    #[tokio::test]
+    async fn a_below_policy_vertex_is_neither_stored_nor_relayed() {
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let node_a = test_manager_with_policy(
+            dir_a.path(),
+            RelayPolicy {
+                min_relay_fee: 1_000,
+                reject_raw_payloads: false,
+            },
+        );
+        let node_b = test_manager(dir_b.path());
+
+        node_a.start().await.unwrap();
+        let addr: SocketAddr = format!("127.0.0.1:{}", node_a.local_port())
+            .parse()
+            .unwrap();
+        node_b.connect_to_peer(addr).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // Park b so its queue gives an exact count of what a relayed.
+        node_a.park_peer(node_b.node_id()).await;
+        let tx = |fee: u64, nonce: u64| TransactionData {
+            source: "spammer".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce,
+            fee,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+
+        // The zero-fee vertex is dropped at the edge: never stored, never
+        // relayed.
+        let spam = DAGVertex::new(tx(0, 0), Vec::new(), 0, 0);
+        node_a
+            .process_message_inner(NetworkMessage::NewVertex(Box::new(spam.clone())), "peer-x")
+            .await;
+        assert!(node_a.engine.get_vertex(&spam.tx_hash).unwrap().is_none());
+        assert!(node_a.parked.read().await[node_b.node_id()].messages.is_empty());
+
+        // A fee-paying vertex passes the same path and is relayed.
+        let paid = DAGVertex::new(tx(1_000, 1), Vec::new(), 0, 0);
+        node_a
+            .process_message_inner(NetworkMessage::NewVertex(Box::new(paid.clone())), "peer-x")
+            .await;
+        assert!(node_a.engine.get_vertex(&paid.tx_hash).unwrap().is_some());
+        assert_eq!(node_a.parked.read().await[node_b.node_id()].messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn connect_and_decode_failures_carry_their_network_error_variant() {
+        // A dial to a dead port is a connection failure.
+        let dir = tempfile::tempdir().unwrap();
+        let manager = test_manager(dir.path());
+        let addr: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let err = manager.connect_to_peer(addr).await.unwrap_err();
+        match err {
+            DAGError::NetworkError(msg) => assert!(msg.contains("connection failed")),
+            other => panic!("expected a connection failure, got {other}"),
+        }
+
+        // An undecodable frame is a serialization failure, and stays one
+        // through the conversion into DAGError.
+        let err = decode_message(&[0xff; 3]).unwrap_err();
+        assert!(matches!(err, NetworkError::SerializationError(_)));
+        assert!(matches!(
+            DAGError::from(err),
+            DAGError::SerializationError(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn a_vertex_is_broadcast_only_once_within_the_seen_ttl() {
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let node_a = test_manager(dir_a.path());
+        let node_b = test_manager(dir_b.path());
+
+        node_a.start().await.unwrap();
+        let addr: SocketAddr = format!("127.0.0.1:{}", node_a.local_port())
+            .parse()
+            .unwrap();
+        node_b.connect_to_peer(addr).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // Park b so its queue gives an exact count of what a sent.
+        node_a.park_peer(node_b.node_id()).await;
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: 0,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let vertex = DAGVertex::new(tx, Vec::new(), 0, 0);
+        node_a.engine.insert_vertex(vertex.clone()).unwrap();
+
+        // The create path and the gossip path may both try to send the
+        // same vertex; only the first attempt goes out.
+        for _ in 0..2 {
+            node_a
+                .broadcast_message(NetworkMessage::NewVertex(Box::new(vertex.clone())))
+                .await;
+        }
+        let parked = node_a.parked.read().await;
+        assert_eq!(parked[node_b.node_id()].messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unsupported_protocol_versions_are_rejected_at_handshake() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = test_manager(dir.path());
+        manager.start().await.unwrap();
+
+        let addr = format!("127.0.0.1:{}", manager.local_port());
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        let raw_identity = NodeIdentity::generate();
+        let msg = NetworkMessage::Handshake {
+            node_id: raw_identity.node_id().to_string(),
+            version: "99.0.0".into(),
+            listen_port: 0,
+            public_key: raw_identity.public_key_bytes().to_vec(),
+            nonce: 1,
+            signature: raw_identity.sign_nonce(1),
+        };
+        let bytes = bincode::serialize(&msg).unwrap();
+        let mut frame = (bytes.len() as u32).to_le_bytes().to_vec();
+        frame.extend(bytes);
+        stream.write_all(&frame).await.unwrap();
+
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await.unwrap();
+        let mut buf = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        stream.read_exact(&mut buf).await.unwrap();
+        match bincode::deserialize::<NetworkMessage>(&buf).unwrap() {
+            NetworkMessage::HandshakeResponse { accepted, .. } => assert!(!accepted),
+            other => panic!("expected handshake response, got {other:?}"),
+        }
+        assert_eq!(manager.peer_count().await, 0);
+
+        assert!(version_supported(PROTOCOL_VERSION));
+        assert!(!version_supported("0.9.0"));
+        assert!(!version_supported("bogus"));
+    }
+
+    #[tokio::test]
+    async fn ping_round_trips_record_peer_latency() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = test_manager(dir.path());
+        manager.start().await.unwrap();
+
+        let addr = format!("127.0.0.1:{}", manager.local_port());
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        let send = |msg: &NetworkMessage| {
+            let bytes = bincode::serialize(msg).unwrap();
+            let mut frame = (bytes.len() as u32).to_le_bytes().to_vec();
+            frame.extend(bytes);
+            frame
+        };
+        let read_msg = |buf: Vec<u8>| bincode::deserialize::<NetworkMessage>(&buf).unwrap();
+        let raw_identity = NodeIdentity::generate();
+        stream
+            .write_all(&send(&NetworkMessage::Handshake {
+                node_id: raw_identity.node_id().to_string(),
+                version: PROTOCOL_VERSION.into(),
+                listen_port: 0,
+                public_key: raw_identity.public_key_bytes().to_vec(),
+                nonce: 1,
+                signature: raw_identity.sign_nonce(1),
+            }))
+            .await
+            .unwrap();
+        // Consume the handshake response.
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await.unwrap();
+        let mut buf = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert!(matches!(
+            read_msg(buf),
+            NetworkMessage::HandshakeResponse { accepted: true, .. }
+        ));
+
+        manager.ping_peers().await;
+        // The maintenance loop may have pinged too; answer every ping, each
+        // after a simulated 50ms of wire delay, until a round trip lands.
+        let mut latency = None;
+        for _ in 0..4 {
+            let mut len_buf = [0u8; 4];
+            let read = tokio::time::timeout(
+                Duration::from_millis(500),
+                stream.read_exact(&mut len_buf),
+            )
+            .await;
+            if read.is_err() {
+                break;
+            }
+            read.unwrap().unwrap();
+            let mut buf = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            stream.read_exact(&mut buf).await.unwrap();
+            let NetworkMessage::Ping { nonce } = read_msg(buf) else {
+                continue;
+            };
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream
+                .write_all(&send(&NetworkMessage::Pong { nonce }))
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let details = manager.get_peer_details().await;
+            assert_eq!(details.len(), 1);
+            if let Some(measured) = details[0].latency_ms {
+                latency = Some(measured);
+                break;
+            }
+        }
+        let latency = latency.expect("latency not recorded");
+        assert!(latency >= 45.0, "latency {latency} below the injected delay");
+        assert!(latency < 5_000.0, "latency {latency} implausibly high");
+        assert_eq!(
+            manager.best_sync_peer().await.as_deref(),
+            Some(raw_identity.node_id())
+        );
+    }
+
+    #[tokio::test]
+    async fn corrupt_frames_are_penalized_without_killing_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = test_manager(dir.path());
+        manager.start().await.unwrap();
+
+        let addr = format!("127.0.0.1:{}", manager.local_port());
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        let send = |msg: &NetworkMessage| {
+            let bytes = bincode::serialize(msg).unwrap();
+            let mut frame = (bytes.len() as u32).to_le_bytes().to_vec();
+            frame.extend(bytes);
+            frame
+        };
+        let raw_identity = NodeIdentity::generate();
+        stream
+            .write_all(&send(&NetworkMessage::Handshake {
+                node_id: raw_identity.node_id().to_string(),
+                version: PROTOCOL_VERSION.into(),
+                listen_port: 0,
+                public_key: raw_identity.public_key_bytes().to_vec(),
+                nonce: 1,
+                signature: raw_identity.sign_nonce(1),
+            }))
+            .await
+            .unwrap();
+        // Consume the handshake response frame.
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await.unwrap();
+        let mut buf = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: 0,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let first = DAGVertex::new(tx.clone(), Vec::new(), 0, 0);
+        let mut second_tx = tx;
+        second_tx.nonce = 1;
+        let second = DAGVertex::new(second_tx, Vec::new(), 0, 0);
+
+        stream
+            .write_all(&send(&NetworkMessage::NewVertex(Box::new(first.clone()))))
+            .await
+            .unwrap();
+        // A well-framed but undecodable payload.
+        let mut corrupt = 7u32.to_le_bytes().to_vec();
+        corrupt.extend([0xde, 0xad, 0xbe, 0xef, 0x01, 0x02, 0x03]);
+        stream.write_all(&corrupt).await.unwrap();
+        stream
+            .write_all(&send(&NetworkMessage::NewVertex(Box::new(second.clone()))))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(manager.engine.get_vertex(&first.tx_hash).unwrap().is_some());
+        assert!(manager.engine.get_vertex(&second.tx_hash).unwrap().is_some());
+        let peers = manager.get_peer_details().await;
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].score, -5);
+    }
+
+    fn test_manager_with_book(dir: &std::path::Path, book: PathBuf) -> Arc<NetworkManager> {
+        let config = DAGEngineConfig {
+            data_dir: dir.to_path_buf(),
+            ..DAGEngineConfig::default()
+        };
+        let engine = Arc::new(DAGEngine::new(config).unwrap());
+        Arc::new(NetworkManager::new(
+            Arc::new(NodeIdentity::generate()),
+            NetworkConfig {
+                listen_addr: Ipv4Addr::LOCALHOST.into(),
+                port: 0,
+                bootstrap_peers: Vec::new(),
+                max_connections: 50,
+                address_book_path: Some(book),
+                keepalive_secs: 30,
+                read_timeout_secs: 90,
+                relay_policy: None,
+            },
+            engine,
+        ))
+    }
+
+    #[tokio::test]
+    async fn listener_binds_only_the_configured_address() {
+        let dir = tempfile::tempdir().unwrap();
+        // test_manager configures listen_addr 127.0.0.1.
+        let node = test_manager(dir.path());
+        node.start().await.unwrap();
+        assert_eq!(node.local_addr().ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        // The loopback address accepts connections.
+        assert!(TcpStream::connect(node.local_addr()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn own_address_in_discovery_creates_no_self_peer() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = test_manager(dir.path());
+        node.start().await.unwrap();
+        let own_addr: SocketAddr = format!("127.0.0.1:{}", node.local_port())
+            .parse()
+            .unwrap();
+
+        // Discovery path short-circuits on the listen address.
+        node.connect_to_new_peer(own_addr).await;
+        // A direct dial still fails on the node-id check.
+        assert!(node.connect_to_peer(own_addr).await.is_err());
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(node.peer_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn silent_peers_are_dropped_after_the_read_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DAGEngineConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..DAGEngineConfig::default()
+        };
+        let engine = Arc::new(DAGEngine::new(config).unwrap());
+        let manager = Arc::new(NetworkManager::new(
+            Arc::new(NodeIdentity::generate()),
+            NetworkConfig {
+                listen_addr: Ipv4Addr::LOCALHOST.into(),
+                port: 0,
+                bootstrap_peers: Vec::new(),
+                max_connections: 50,
+                address_book_path: None,
+                keepalive_secs: 30,
+                read_timeout_secs: 1,
+                relay_policy: None,
+            },
+            engine,
+        ));
+        manager.start().await.unwrap();
+
+        // Handshake properly, then go completely silent.
+        let addr = format!("127.0.0.1:{}", manager.local_port());
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        let raw_identity = NodeIdentity::generate();
+        let msg = NetworkMessage::Handshake {
+            node_id: raw_identity.node_id().to_string(),
+            version: PROTOCOL_VERSION.into(),
+            listen_port: 0,
+            public_key: raw_identity.public_key_bytes().to_vec(),
+            nonce: 7,
+            signature: raw_identity.sign_nonce(7),
+        };
+        let bytes = bincode::serialize(&msg).unwrap();
+        let mut frame = (bytes.len() as u32).to_le_bytes().to_vec();
+        frame.extend(bytes);
+        stream.write_all(&frame).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(manager.peer_count().await, 1);
+
+        // Pruned by the read timeout, far sooner than the last_seen sweep.
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+        assert_eq!(manager.peer_count().await, 0);
+        drop(stream);
+    }
+
+    #[tokio::test]
+    async fn inbound_peers_beyond_max_connections_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DAGEngineConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..DAGEngineConfig::default()
+        };
+        let engine = Arc::new(DAGEngine::new(config).unwrap());
+        let listener = Arc::new(NetworkManager::new(
+            Arc::new(NodeIdentity::generate()),
+            NetworkConfig {
+                listen_addr: Ipv4Addr::LOCALHOST.into(),
+                port: 0,
+                bootstrap_peers: Vec::new(),
+                max_connections: 2,
+                address_book_path: None,
+                keepalive_secs: 30,
+                read_timeout_secs: 90,
+                relay_policy: None,
+            },
+            engine,
+        ));
+        listener.start().await.unwrap();
+        let addr: SocketAddr = format!("127.0.0.1:{}", listener.local_port())
+            .parse()
+            .unwrap();
+
+        let mut dialers = Vec::new();
+        let mut results = Vec::new();
+        for i in 0..3 {
+            let dir = tempfile::tempdir().unwrap();
+            let dialer = test_manager(dir.path());
+            results.push(dialer.connect_to_peer(addr).await);
+            dialers.push((dir, dialer));
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let _ = i;
+        }
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert!(matches!(results[2], Err(DAGError::NetworkError(_))));
+        assert_eq!(listener.peer_count().await, 2);
+    }
+
+    #[tokio::test]
+    async fn persisted_address_book_seeds_reconnects() {
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let book = dir_b.path().join("peers.json");
+
+        let node_a = test_manager(dir_a.path());
+        node_a.start().await.unwrap();
+        let addr: SocketAddr = format!("127.0.0.1:{}", node_a.local_port())
+            .parse()
+            .unwrap();
+
+        // First run connects once and persists the address.
+        {
+            let node_b = test_manager_with_book(dir_b.path(), book.clone());
+            node_b.connect_to_peer(addr).await.unwrap();
+            node_b.save_address_book().await;
+        }
+        let saved: Vec<SocketAddr> =
+            serde_json::from_slice(&std::fs::read(&book).unwrap()).unwrap();
+        assert_eq!(saved, vec![addr]);
+
+        // A fresh instance with the same book reconnects without bootstrap.
+        let dir_b2 = tempfile::tempdir().unwrap();
+        let node_b2 = test_manager_with_book(dir_b2.path(), book);
+        node_b2.start().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(node_b2.peer_count().await, 1);
+    }
+}
@@ -0,0 +1,1524 @@
+//! HTTP RPC server exposing node queries and transaction submission.
+
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::info;
+use serde_json::json;
+
+use crate::consensus::{EpochBlock, VirtualVotingConsensus, MIN_PERFORMANCE_SCORE};
+use crate::engine::DAGEngine;
+use crate::error::DAGError;
+use crate::ffi::DAGErrorCode;
+use crate::mempool::Mempool;
+use crate::metrics::NodeMetrics;
+use crate::state::{CurrencyRegistry, StateMachine, CS_CURRENCY};
+use crate::storage::Cursor;
+use crate::vertex::{DAGVertex, TransactionData, TransferOutput, VertexHash};
+
+/// Shared context handed to every request handler.
+pub struct RpcContext {
+    pub engine: Arc<DAGEngine>,
+    pub consensus: Arc<RwLock<VirtualVotingConsensus>>,
+    pub mempool: Arc<Mempool>,
+    pub state: Arc<StateMachine>,
+    pub currencies: Arc<CurrencyRegistry>,
+    pub metrics: Arc<RwLock<NodeMetrics>>,
+    pub limits: RpcLimits,
+    /// Bearer token required on mutating endpoints; `None` leaves them open.
+    pub admin_token: Option<String>,
+}
+
+/// Request-handling limits, uniform across all endpoints.
+#[derive(Debug, Clone)]
+pub struct RpcLimits {
+    /// Largest accepted request body; larger bodies get `413`.
+    pub max_body_bytes: usize,
+    /// Per-request deadline; slower requests get `408`.
+    pub request_timeout_ms: u64,
+    /// Most requests served at once; excess requests get `503`.
+    pub max_concurrent: usize,
+    /// Most hashes accepted by one `POST /vertices` lookup.
+    pub max_batch_hashes: usize,
+}
+
+impl Default for RpcLimits {
+    fn default() -> Self {
+        RpcLimits {
+            max_body_bytes: 1024 * 1024,
+            request_timeout_ms: 10_000,
+            max_concurrent: 256,
+            max_batch_hashes: 256,
+        }
+    }
+}
+
+/// The HTTP RPC server.
+pub struct RPCServer {
+    context: Arc<RpcContext>,
+    bind_addr: IpAddr,
+    port: u16,
+}
+
+/// Renders a vertex as client-facing JSON with hex-encoded hashes.
+pub fn vertex_to_json(vertex: &DAGVertex) -> serde_json::Value {
+    json!({
+        "hash": hex::encode(vertex.tx_hash),
+        "logical_clock": vertex.logical_clock,
+        "shard_id": vertex.shard_id,
+        "timestamp": vertex.timestamp,
+        "parents": vertex.parents.iter().map(hex::encode).collect::<Vec<_>>(),
+        "source": vertex.transaction_data.source,
+        "target": vertex.transaction_data.target,
+        "amount": vertex.transaction_data.amount,
+        "currency": vertex.transaction_data.currency,
+        "nonce": vertex.transaction_data.nonce,
+        "fee": vertex.transaction_data.fee,
+    })
+}
+
+/// Renders an epoch block as client-facing JSON with hex-encoded hashes.
+fn block_to_json(block: &EpochBlock) -> serde_json::Value {
+    json!({
+        "number": block.number,
+        "hash": hex::encode(block.hash()),
+        "finalized_vertices": block
+            .finalized_vertices
+            .iter()
+            .map(hex::encode)
+            .collect::<Vec<_>>(),
+        "state_root": hex::encode(block.state_root),
+        "prev_block_hash": hex::encode(block.prev_block_hash),
+    })
+}
+
+/// Standard error body returned by every failing endpoint, so clients can
+/// branch on `code` (the [`DAGErrorCode`] value) instead of parsing messages.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonError {
+    pub code: u32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+/// Builds the standard error response.
+fn error_response(code: DAGErrorCode, message: impl Into<String>, status: StatusCode) -> Response<Body> {
+    let body = JsonError {
+        code: code as u32,
+        message: message.into(),
+        details: None,
+    };
+    json_response(status, serde_json::to_value(body).unwrap())
+}
+
+/// HTTP status a [`DAGErrorCode`] maps to.
+fn status_for(code: DAGErrorCode) -> StatusCode {
+    match code {
+        DAGErrorCode::InvalidVertex
+        | DAGErrorCode::ValidationError
+        | DAGErrorCode::SerializationError
+        | DAGErrorCode::InvalidSignature
+        | DAGErrorCode::InsufficientBalance
+        | DAGErrorCode::InsufficientFee
+        | DAGErrorCode::NonceMismatch => StatusCode::BAD_REQUEST,
+        DAGErrorCode::AlreadyExists => StatusCode::CONFLICT,
+        DAGErrorCode::NotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The standard response for a [`DAGError`], with the status derived from
+/// its error code.
+fn dag_error_response(e: &DAGError) -> Response<Body> {
+    let code = DAGErrorCode::from(e);
+    error_response(code, e.to_string(), status_for(code))
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    let mut response = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    add_cors_headers(&mut response);
+    response
+}
+
+fn add_cors_headers(response: &mut Response<Body>) {
+    let headers = response.headers_mut();
+    headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
+    headers.insert(
+        "Access-Control-Allow-Methods",
+        "GET, POST, OPTIONS".parse().unwrap(),
+    );
+    headers.insert(
+        "Access-Control-Allow-Headers",
+        "Content-Type, Authorization".parse().unwrap(),
+    );
+}
+
+/// Parses a `k=v&k2=v2` query string.
+fn parse_query(query: Option<&str>) -> std::collections::HashMap<String, String> {
+    query
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            Some((k.to_string(), v.to_string()))
+        })
+        .collect()
+}
+
+fn parse_hash(hex_str: &str) -> Option<VertexHash> {
+    let bytes = hex::decode(hex_str).ok()?;
+    bytes.as_slice().try_into().ok()
+}
+
+/// Cursor query-string form: `<clock>-<64 hex chars>`.
+fn format_cursor(cursor: Cursor) -> String {
+    format!("{}-{}", cursor.clock, hex::encode(cursor.hash))
+}
+
+fn parse_cursor(s: &str) -> Option<Cursor> {
+    let (clock, hash) = s.split_once('-')?;
+    Some(Cursor {
+        clock: clock.parse().ok()?,
+        hash: parse_hash(hash)?,
+    })
+}
+
+async fn handle_request(
+    context: Arc<RpcContext>,
+    semaphore: Arc<tokio::sync::Semaphore>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    // The permit is held for the whole request, including body reads, so a
+    // connection flood saturates the semaphore instead of the node.
+    let Ok(_permit) = semaphore.try_acquire() else {
+        return Ok(error_response(
+            DAGErrorCode::InternalError,
+            "server at capacity",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    let deadline = std::time::Duration::from_millis(context.limits.request_timeout_ms);
+    match tokio::time::timeout(deadline, route_request(context, req)).await {
+        Ok(response) => Ok(response),
+        Err(_) => Ok(error_response(
+            DAGErrorCode::InternalError,
+            "request timed out",
+            StatusCode::REQUEST_TIMEOUT,
+        )),
+    }
+}
+
+/// Whether the request carries the configured admin bearer token. Requests
+/// are always authorized when no token is configured.
+fn is_authorized(context: &RpcContext, req: &Request<Body>) -> bool {
+    let Some(token) = &context.admin_token else {
+        return true;
+    };
+    req.headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|presented| constant_time_eq(presented.as_bytes(), token.as_bytes()))
+}
+
+/// Equality that always inspects every byte, so a mismatch never
+/// short-circuits and the admin token cannot be probed position by
+/// position through response timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn route_request(context: Arc<RpcContext>, req: Request<Body>) -> Response<Body> {
+    let path = req.uri().path().to_string();
+    // Every mutating endpoint is a POST; reads stay open.
+    if req.method() == Method::POST && !is_authorized(&context, &req) {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "missing or invalid admin token",
+            StatusCode::UNAUTHORIZED,
+        );
+    }
+    match (req.method(), path.as_str()) {
+        (&Method::GET, "/" | "/health") => {
+            if context.engine.is_storage_degraded() {
+                json_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    json!({"status": "degraded", "reason": "storage writes failing"}),
+                )
+            } else {
+                json_response(StatusCode::OK, json!({"status": "ok"}))
+            }
+        }
+        (&Method::GET, "/stats") => {
+            let stats = context.engine.storage().get_stats();
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "total_vertices": stats.total_vertices,
+                    "logical_size_bytes": stats.logical_size_bytes,
+                    "physical_size_bytes": stats.physical_size_bytes,
+                    "cache_hits": stats.cache_hits,
+                    "cache_misses": stats.cache_misses,
+                    "read_operations": stats.read_operations,
+                    "write_operations": stats.write_operations,
+                    "mempool_size": context.mempool.len(),
+                }),
+            )
+        }
+        (&Method::GET, "/metrics") => {
+            let metrics = context.metrics.read().unwrap().clone();
+            json_response(StatusCode::OK, serde_json::to_value(metrics).unwrap())
+        }
+        (&Method::GET, "/validators") => {
+            let consensus = context.consensus.read().unwrap();
+            let validators: Vec<_> = consensus
+                .list_validators()
+                .into_iter()
+                .map(|(id, v)| {
+                    json!({
+                        "validator_id": id,
+                        "stake": v.stake,
+                        "tier": format!("{:?}", v.tier),
+                        "performance_score": v.performance_score,
+                        "last_activity": v.last_activity,
+                        "active": v.performance_score >= MIN_PERFORMANCE_SCORE,
+                    })
+                })
+                .collect();
+            json_response(StatusCode::OK, json!({ "validators": validators }))
+        }
+        (&Method::GET, "/validators/stake-distribution") => {
+            let consensus = context.consensus.read().unwrap();
+            let distribution: serde_json::Map<String, serde_json::Value> = consensus
+                .stake_distribution()
+                .into_iter()
+                .map(|(tier, (count, total_stake))| {
+                    (
+                        format!("{tier:?}"),
+                        json!({"count": count, "total_stake": total_stake}),
+                    )
+                })
+                .collect();
+            json_response(StatusCode::OK, json!({ "distribution": distribution }))
+        }
+        (&Method::GET, "/checkpoints/latest") => {
+            let consensus = context.consensus.read().unwrap();
+            match consensus.latest_checkpoint() {
+                Some(checkpoint) => json_response(
+                    StatusCode::OK,
+                    json!({
+                        "clock": checkpoint.clock,
+                        "state_root": hex::encode(checkpoint.state_root),
+                        "shard_roots": checkpoint
+                            .shard_roots
+                            .iter()
+                            .map(|(id, root)| json!({"shard_id": id, "root": hex::encode(root)}))
+                            .collect::<Vec<_>>(),
+                        "timestamp": checkpoint.timestamp,
+                        "vote_count": checkpoint.aggregate.vote_count,
+                        "aggregate_signature": hex::encode(&checkpoint.aggregate.aggregate_signature),
+                        "participants": hex::encode(&checkpoint.aggregate.participants),
+                    }),
+                ),
+                None => error_response(
+                    DAGErrorCode::NotFound,
+                    "no checkpoint yet",
+                    StatusCode::NOT_FOUND,
+                ),
+            }
+        }
+        (&Method::GET, "/block/latest") => {
+            let consensus = context.consensus.read().unwrap();
+            match consensus.latest_block() {
+                Some(block) => json_response(StatusCode::OK, block_to_json(block)),
+                None => error_response(
+                    DAGErrorCode::NotFound,
+                    "no blocks yet",
+                    StatusCode::NOT_FOUND,
+                ),
+            }
+        }
+        (&Method::GET, p) if p.starts_with("/block/") => {
+            let number_part = p.trim_start_matches("/block/");
+            let Ok(number) = number_part.parse::<u64>() else {
+                return error_response(
+                    DAGErrorCode::ValidationError,
+                    "block number must be an integer",
+                    StatusCode::BAD_REQUEST,
+                );
+            };
+            let consensus = context.consensus.read().unwrap();
+            match consensus.get_block(number) {
+                Some(block) => json_response(StatusCode::OK, block_to_json(block)),
+                None => error_response(
+                    DAGErrorCode::NotFound,
+                    format!("no block {number}"),
+                    StatusCode::NOT_FOUND,
+                ),
+            }
+        }
+        (&Method::GET, "/tips") => {
+            let tips: Vec<String> = context.engine.get_tips().iter().map(hex::encode).collect();
+            json_response(StatusCode::OK, json!({ "tips": tips }))
+        }
+        (&Method::GET, "/vertices") => handle_vertex_range(&context, req.uri().query()),
+        (&Method::GET, "/recent") => handle_recent(&context, req.uri().query()),
+        (&Method::GET, p) if p.starts_with("/address/") && p.ends_with("/txs") => {
+            let address = p.trim_start_matches("/address/").trim_end_matches("/txs");
+            handle_address_txs(&context, address, req.uri().query())
+        }
+        (&Method::GET, "/shard-of") => {
+            let namespace = req
+                .uri()
+                .query()
+                .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("namespace=")));
+            let Some(namespace) = namespace else {
+                return error_response(
+                    DAGErrorCode::ValidationError,
+                    "namespace query parameter is required",
+                    StatusCode::BAD_REQUEST,
+                );
+            };
+            let coordinator = context.engine.shard_coordinator();
+            let shard_id = coordinator.assign_shard(namespace);
+            let (start, end) = coordinator.shard_range(shard_id);
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "namespace": namespace,
+                    "shard_id": shard_id,
+                    "range_start": start,
+                    "range_end": end,
+                }),
+            )
+        }
+        (&Method::GET, p) if p.starts_with("/shards/") && p.ends_with("/vertices") => {
+            let id_part = p
+                .trim_start_matches("/shards/")
+                .trim_end_matches("/vertices");
+            handle_shard_vertices(&context, id_part, req.uri().query())
+        }
+        (&Method::GET, p) if p.starts_with("/vertex/") && p.ends_with("/ancestors") => {
+            let hash_part = p
+                .trim_start_matches("/vertex/")
+                .trim_end_matches("/ancestors");
+            handle_vertex_ancestors(&context, hash_part, req.uri().query())
+        }
+        (&Method::GET, p) if p.starts_with("/vertex/") && p.ends_with("/votes") => {
+            let hash_part = p.trim_start_matches("/vertex/").trim_end_matches("/votes");
+            handle_vertex_votes(&context, hash_part)
+        }
+        (&Method::GET, p) if p.starts_with("/vertex/") => {
+            handle_get_vertex(&context, p.trim_start_matches("/vertex/"))
+        }
+        (&Method::GET, p) if p.starts_with("/balance/") => {
+            let address = p.trim_start_matches("/balance/");
+            let currency = req
+                .uri()
+                .query()
+                .and_then(|q| {
+                    q.split('&')
+                        .find_map(|pair| pair.strip_prefix("currency="))
+                        .and_then(|v| v.parse::<u32>().ok())
+                })
+                .unwrap_or(CS_CURRENCY);
+            let balance = context.state.get_token_balance(address, currency);
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "address": address,
+                    "currency": currency,
+                    "symbol": context.currencies.symbol(currency),
+                    "balance": balance,
+                    "formatted": context.currencies.format_amount(balance, currency),
+                }),
+            )
+        }
+        (&Method::GET, "/supply") => {
+            let supply = context.state.supply();
+            let mut value = serde_json::to_value(supply).unwrap();
+            value["total_staked"] =
+                serde_json::Value::from(context.consensus.read().unwrap().total_stake());
+            json_response(StatusCode::OK, value)
+        }
+        (&Method::GET, "/consensus/info") => {
+            let info = context.engine.consensus_info();
+            json_response(StatusCode::OK, serde_json::to_value(info).unwrap())
+        }
+        (&Method::GET, "/consensus/status") => {
+            let consensus = context.consensus.read().unwrap();
+            let round = consensus.current_round();
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "round": round,
+                    "epoch": consensus.current_epoch(),
+                    "validators": consensus.validator_count(),
+                    "total_stake": consensus.total_stake(),
+                    "active_stake": consensus.active_stake(),
+                    "latest_block": consensus.latest_block().map(|b| b.number),
+                    "finalized_last_round": consensus.finalized_order(round).len(),
+                }),
+            )
+        }
+        (&Method::POST, "/consensus/round") => handle_consensus_round(&context),
+        (&Method::POST, "/flush") => match context.engine.flush() {
+            Ok(bytes) => json_response(StatusCode::OK, json!({ "bytes_flushed": bytes })),
+            Err(e) => dag_error_response(&e),
+        },
+        (&Method::POST, "/create") => handle_create_vertex(&context, req).await,
+        (&Method::POST, "/tx") => handle_submit_tx(&context, req).await,
+        (&Method::POST, "/tx/batch") => handle_submit_batch(&context, req).await,
+        (&Method::POST, "/vertices") => handle_get_vertices(&context, req).await,
+        (&Method::OPTIONS, _) => {
+            // CORS preflight: no body, just the allow headers.
+            let mut response = Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap();
+            add_cors_headers(&mut response);
+            response
+        }
+        _ => error_response(DAGErrorCode::NotFound, "not found", StatusCode::NOT_FOUND),
+    }
+}
+
+/// Reads a request body, enforcing the configured size cap.
+async fn read_body_limited(req: Request<Body>, max: usize) -> Result<Vec<u8>, Response<Body>> {
+    use hyper::body::HttpBody;
+
+    let mut body = req.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| {
+            error_response(
+                DAGErrorCode::NetworkError,
+                format!("body read failed: {e}"),
+                StatusCode::BAD_REQUEST,
+            )
+        })?;
+        if bytes.len() + chunk.len() > max {
+            return Err(error_response(
+                DAGErrorCode::ValidationError,
+                format!("body exceeds {max} bytes"),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Clock-range vertex listing with cursor paging.
+/// `GET /recent?limit=`: the most recently inserted vertices, newest first.
+fn handle_recent(context: &RpcContext, query: Option<&str>) -> Response<Body> {
+    let limit = parse_query(query)
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(20);
+    match context.engine.recent_vertices(limit) {
+        Ok(vertices) => json_response(
+            StatusCode::OK,
+            json!({
+                "vertices": vertices
+                    .iter()
+                    .map(|v| {
+                        json!({
+                            "hash": hex::encode(v.tx_hash),
+                            "logical_clock": v.logical_clock,
+                            "timestamp": v.timestamp,
+                            "parent_count": v.parents.len(),
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+        ),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+fn handle_vertex_range(context: &RpcContext, query: Option<&str>) -> Response<Body> {
+    let params = parse_query(query);
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100);
+    // `from_time`/`to_time` (epoch millis) select the time index instead of
+    // the clock index.
+    if params.contains_key("from_time") || params.contains_key("to_time") {
+        let from = params
+            .get("from_time")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let to = params
+            .get("to_time")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(u64::MAX);
+        return match context.engine.get_vertices_by_time_range(from, to, limit) {
+            Ok(vertices) => json_response(
+                StatusCode::OK,
+                json!({
+                    "vertices": vertices.iter().map(vertex_to_json).collect::<Vec<_>>(),
+                }),
+            ),
+            Err(e) => dag_error_response(&e),
+        };
+    }
+    let start = params.get("start").and_then(|v| v.parse().ok()).unwrap_or(0);
+    let end = params
+        .get("end")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(u64::MAX);
+    let after = match params.get("after") {
+        Some(cursor) => match parse_cursor(cursor) {
+            Some(cursor) => Some(cursor),
+            None => {
+                return error_response(
+                    DAGErrorCode::ValidationError,
+                    "after must be <clock>-<64 hex chars>",
+                    StatusCode::BAD_REQUEST,
+                );
+            }
+        },
+        None => None,
+    };
+    match context
+        .engine
+        .get_vertices_by_clock_range(start, end, limit, after)
+    {
+        Ok((vertices, next)) => json_response(
+            StatusCode::OK,
+            json!({
+                "vertices": vertices.iter().map(vertex_to_json).collect::<Vec<_>>(),
+                "next_after": next.map(format_cursor),
+            }),
+        ),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+fn handle_address_txs(context: &RpcContext, address: &str, query: Option<&str>) -> Response<Body> {
+    let params = parse_query(query);
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100);
+    let after = match params.get("after") {
+        Some(cursor) => match parse_cursor(cursor) {
+            Some(cursor) => Some(cursor),
+            None => {
+                return error_response(
+                    DAGErrorCode::ValidationError,
+                    "after must be <clock>-<64 hex chars>",
+                    StatusCode::BAD_REQUEST,
+                );
+            }
+        },
+        None => None,
+    };
+    match context
+        .engine
+        .storage()
+        .get_transactions_for_address(address, limit, after)
+    {
+        Ok((vertices, next)) => json_response(
+            StatusCode::OK,
+            json!({
+                "address": address,
+                "transactions": vertices.iter().map(vertex_to_json).collect::<Vec<_>>(),
+                "next_after": next.map(format_cursor),
+            }),
+        ),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+fn handle_shard_vertices(
+    context: &RpcContext,
+    id_part: &str,
+    query: Option<&str>,
+) -> Response<Body> {
+    let Ok(shard_id) = id_part.trim_matches('/').parse::<u32>() else {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "shard id must be an integer",
+            StatusCode::BAD_REQUEST,
+        );
+    };
+    let params = parse_query(query);
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100);
+    let after = match params.get("after") {
+        Some(cursor) => match parse_hash(cursor) {
+            Some(hash) => Some(hash),
+            None => {
+                return error_response(
+                    DAGErrorCode::ValidationError,
+                    "after must be 64 hex chars",
+                    StatusCode::BAD_REQUEST,
+                );
+            }
+        },
+        None => None,
+    };
+    match context.engine.get_vertices_in_shard(shard_id, limit, after) {
+        Ok(vertices) => {
+            // A short page means the shard is exhausted; like the
+            // clock-range endpoint, report no cursor instead of making
+            // clients fetch one extra empty page.
+            let next = if vertices.len() < limit {
+                None
+            } else {
+                vertices.last().map(|v| hex::encode(v.tx_hash))
+            };
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "shard_id": shard_id,
+                    "vertices": vertices.iter().map(vertex_to_json).collect::<Vec<_>>(),
+                    "next_after": next,
+                }),
+            )
+        }
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+fn handle_vertex_ancestors(
+    context: &RpcContext,
+    hash_hex: &str,
+    query: Option<&str>,
+) -> Response<Body> {
+    let Some(hash) = parse_hash(hash_hex.trim_matches('/')) else {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "hash must be 64 hex chars",
+            StatusCode::BAD_REQUEST,
+        );
+    };
+    let depth = parse_query(query)
+        .get("depth")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(100);
+    match context.engine.get_ancestors(&hash, depth) {
+        Ok(ancestors) => json_response(
+            StatusCode::OK,
+            json!({
+                "hash": hex::encode(hash),
+                "ancestors": ancestors.iter().map(vertex_to_json).collect::<Vec<_>>(),
+            }),
+        ),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+/// `GET /vertex/{hash}/votes`: the vote tally recorded for a vertex.
+fn handle_vertex_votes(context: &RpcContext, hash_hex: &str) -> Response<Body> {
+    let Some(hash) = parse_hash(hash_hex.trim_matches('/')) else {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "hash must be 64 hex chars",
+            StatusCode::BAD_REQUEST,
+        );
+    };
+    match context.consensus.read().unwrap().vote_summary(&hash) {
+        Some(summary) => json_response(
+            StatusCode::OK,
+            json!({
+                "hash": hex::encode(summary.vertex_hash),
+                "approvals": summary.approvals,
+                "rejections": summary.rejections,
+                "voting_stake": summary.voting_stake,
+                "required_stake": summary.required_stake,
+                "consensus_reached": summary.consensus_reached,
+            }),
+        ),
+        None => error_response(
+            DAGErrorCode::NotFound,
+            "no votes recorded for vertex",
+            StatusCode::NOT_FOUND,
+        ),
+    }
+}
+
+fn handle_get_vertex(context: &RpcContext, hash_hex: &str) -> Response<Body> {
+    let Some(hash) = parse_hash(hash_hex) else {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "hash must be 64 hex chars",
+            StatusCode::BAD_REQUEST,
+        );
+    };
+    match context.engine.get_vertex(&hash) {
+        Ok(Some(vertex)) => {
+            let mut body = vertex_to_json(&vertex);
+            if let Ok(weight) = context.engine.cumulative_weight(&hash) {
+                body["weight"] = json!(weight);
+            }
+            json_response(StatusCode::OK, body)
+        }
+        Ok(None) => error_response(
+            DAGErrorCode::NotFound,
+            "vertex not found",
+            StatusCode::NOT_FOUND,
+        ),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+/// Creates a bare vertex directly from client-supplied fields.
+async fn handle_create_vertex(context: &RpcContext, req: Request<Body>) -> Response<Body> {
+    let body = match read_body_limited(req, context.limits.max_body_bytes).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let value: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            return error_response(
+                DAGErrorCode::SerializationError,
+                format!("invalid json: {e}"),
+                StatusCode::BAD_REQUEST,
+            );
+        }
+    };
+    let vertex = match create_simple_vertex(&context.engine, &value) {
+        Ok(vertex) => vertex,
+        Err(e) => return dag_error_response(&e),
+    };
+    let hash = vertex.tx_hash;
+    match context.engine.insert_vertex(vertex) {
+        Ok(()) => json_response(StatusCode::OK, json!({ "hash": hex::encode(hash) })),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+/// Builds a vertex from raw request fields, selecting real parents and the
+/// next logical clock through the engine like the node's own create path.
+fn create_simple_vertex(
+    engine: &DAGEngine,
+    value: &serde_json::Value,
+) -> Result<DAGVertex, DAGError> {
+    let tx = TransactionData {
+        source: value
+            .get("source")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        target: value
+            .get("target")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        amount: value.get("amount").and_then(|v| v.as_u64()).unwrap_or(0),
+        currency: value.get("currency").and_then(|v| v.as_u64()).unwrap_or(1) as u32,
+        nonce: value.get("nonce").and_then(|v| v.as_u64()).unwrap_or(0),
+        fee: value.get("fee").and_then(|v| v.as_u64()).unwrap_or(0),
+        user_data: value
+            .get("user_data")
+            .and_then(|v| v.as_str())
+            .map(|s| s.as_bytes().to_vec())
+            .unwrap_or_default(),
+        outputs: parse_outputs(value),
+    };
+    let mut parents = engine.select_parent_vertices()?;
+    if parents.len() < 2 {
+        // Too few real vertices to satisfy the two-parent rule; start
+        // another root instead of fabricating references.
+        parents.clear();
+    }
+    let clock = engine.next_logical_clock(&parents)?;
+    let shard = engine.shard_coordinator().assign_shard(&tx.source);
+    Ok(DAGVertex::new(tx, parents, clock, shard))
+}
+
+/// Parses an optional `outputs` array of `{target, amount, currency}` objects.
+fn parse_outputs(value: &serde_json::Value) -> Vec<TransferOutput> {
+    value
+        .get("outputs")
+        .and_then(|v| v.as_array())
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|entry| TransferOutput {
+                    target: entry
+                        .get("target")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    amount: entry.get("amount").and_then(|v| v.as_u64()).unwrap_or(0),
+                    currency: entry.get("currency").and_then(|v| v.as_u64()).unwrap_or(1)
+                        as u32,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Accepts a multi-output transfer and queues it as one transaction.
+async fn handle_submit_batch(context: &RpcContext, req: Request<Body>) -> Response<Body> {
+    let body = match read_body_limited(req, context.limits.max_body_bytes).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let value: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            return error_response(
+                DAGErrorCode::SerializationError,
+                format!("invalid json: {e}"),
+                StatusCode::BAD_REQUEST,
+            );
+        }
+    };
+    let outputs = parse_outputs(&value);
+    if outputs.is_empty() {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "outputs must be a non-empty array",
+            StatusCode::BAD_REQUEST,
+        );
+    }
+    let tx = TransactionData {
+        source: value
+            .get("source")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        target: value
+            .get("target")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        amount: value.get("amount").and_then(|v| v.as_u64()).unwrap_or(0),
+        currency: value.get("currency").and_then(|v| v.as_u64()).unwrap_or(1) as u32,
+        nonce: value.get("nonce").and_then(|v| v.as_u64()).unwrap_or(0),
+        fee: value.get("fee").and_then(|v| v.as_u64()).unwrap_or(0),
+        user_data: Vec::new(),
+        outputs,
+    };
+    match context.mempool.submit(tx) {
+        Ok(id) => json_response(StatusCode::OK, json!({ "tx_id": hex::encode(id) })),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+/// `POST /vertices`: bulk lookup of `{"hashes": [hex...]}`, returning one
+/// slot per requested hash with `null` marking a miss.
+async fn handle_get_vertices(context: &RpcContext, req: Request<Body>) -> Response<Body> {
+    let body = match read_body_limited(req, context.limits.max_body_bytes).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let value: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            return error_response(
+                DAGErrorCode::SerializationError,
+                format!("invalid json: {e}"),
+                StatusCode::BAD_REQUEST,
+            );
+        }
+    };
+    let Some(hex_hashes) = value.get("hashes").and_then(|v| v.as_array()) else {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            "hashes must be an array",
+            StatusCode::BAD_REQUEST,
+        );
+    };
+    if hex_hashes.len() > context.limits.max_batch_hashes {
+        return error_response(
+            DAGErrorCode::ValidationError,
+            format!("at most {} hashes per request", context.limits.max_batch_hashes),
+            StatusCode::BAD_REQUEST,
+        );
+    }
+    let mut hashes = Vec::with_capacity(hex_hashes.len());
+    for hex_hash in hex_hashes {
+        let Some(hash) = hex_hash.as_str().and_then(parse_hash) else {
+            return error_response(
+                DAGErrorCode::ValidationError,
+                "every hash must be 64 hex chars",
+                StatusCode::BAD_REQUEST,
+            );
+        };
+        hashes.push(hash);
+    }
+    match context.engine.get_vertices(&hashes) {
+        Ok(vertices) => json_response(
+            StatusCode::OK,
+            json!({
+                "vertices": vertices
+                    .iter()
+                    .map(|slot| match slot {
+                        Some(vertex) => vertex_to_json(vertex),
+                        None => serde_json::Value::Null,
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+        ),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+/// Runs one consensus round and reports what it finalized, so automated
+/// harnesses can drive consensus instead of waiting for the round timer.
+fn handle_consensus_round(context: &RpcContext) -> Response<Body> {
+    let pending = context.engine.pending_finality_count();
+    match context.engine.process_consensus_round() {
+        Ok(proofs) => {
+            let round = context.consensus.read().unwrap().current_round();
+            let proofs_json: Vec<_> = proofs
+                .iter()
+                .map(|p| {
+                    json!({
+                        "vertex_hash": hex::encode(p.vertex_hash),
+                        "round": p.round,
+                        "timestamp": p.timestamp,
+                        "supporting_stake": p.supporting_stake,
+                        "total_stake": p.total_stake,
+                    })
+                })
+                .collect();
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "round": round,
+                    "processed": pending,
+                    "finalized": proofs.len(),
+                    "proofs": proofs_json,
+                }),
+            )
+        }
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+async fn handle_submit_tx(context: &RpcContext, req: Request<Body>) -> Response<Body> {
+    let body = match read_body_limited(req, context.limits.max_body_bytes).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let tx: TransactionData = match serde_json::from_slice(&body) {
+        Ok(tx) => tx,
+        Err(e) => {
+            return error_response(
+                DAGErrorCode::SerializationError,
+                format!("invalid transaction: {e}"),
+                StatusCode::BAD_REQUEST,
+            );
+        }
+    };
+    match context.mempool.submit(tx) {
+        Ok(id) => json_response(StatusCode::OK, json!({ "tx_id": hex::encode(id) })),
+        Err(e) => dag_error_response(&e),
+    }
+}
+
+impl RPCServer {
+    pub fn new(context: RpcContext, bind_addr: IpAddr, port: u16) -> Self {
+        RPCServer {
+            context: Arc::new(context),
+            bind_addr,
+            port,
+        }
+    }
+
+    /// Binds and spawns the server, returning the bound address.
+    pub async fn start(&self) -> Result<SocketAddr, hyper::Error> {
+        let context = self.context.clone();
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(
+            context.limits.max_concurrent.max(1),
+        ));
+        let make_svc = make_service_fn(move |_conn| {
+            let context = context.clone();
+            let semaphore = semaphore.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle_request(context.clone(), semaphore.clone(), req)
+                }))
+            }
+        });
+        let addr = SocketAddr::new(self.bind_addr, self.port);
+        let server = Server::try_bind(&addr)?.serve(make_svc);
+        let local_addr = server.local_addr();
+        info!("rpc server on {local_addr}");
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                log::error!("rpc server error: {e}");
+            }
+        });
+        Ok(local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::engine::DAGEngineConfig;
+    use crate::mempool::MempoolConfig;
+
+    async fn start_test_server(dir: &std::path::Path) -> (SocketAddr, Arc<RpcContext>) {
+        let config = DAGEngineConfig {
+            data_dir: dir.to_path_buf(),
+            ..DAGEngineConfig::default()
+        };
+        let engine = Arc::new(DAGEngine::new(config).unwrap());
+        let context = RpcContext {
+            consensus: engine.consensus().clone(),
+            engine,
+            mempool: Arc::new(Mempool::new(MempoolConfig::default())),
+            state: Arc::new(StateMachine::new()),
+            currencies: Arc::new(CurrencyRegistry::default()),
+            metrics: Arc::new(RwLock::new(NodeMetrics::default())),
+            limits: RpcLimits::default(),
+            admin_token: None,
+        };
+        let server = RPCServer::new(context, Ipv4Addr::LOCALHOST.into(), 0);
+        let shared = server.context.clone();
+        let addr = server.start().await.unwrap();
+        (addr, shared)
+    }
+
+    async fn get_json(addr: SocketAddr, path: &str) -> (StatusCode, serde_json::Value) {
+        let client = hyper::Client::new();
+        let uri: hyper::Uri = format!("http://{addr}{path}").parse().unwrap();
+        let resp = client.get(uri).await.unwrap();
+        let status = resp.status();
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_the_concurrency_limit_get_503() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, context) = start_test_server(dir.path()).await;
+        // The shared server allows 256 in flight; start one that allows 1.
+        let engine = context.engine.clone();
+        let limited = RpcContext {
+            consensus: engine.consensus().clone(),
+            engine,
+            mempool: context.mempool.clone(),
+            state: context.state.clone(),
+            currencies: context.currencies.clone(),
+            metrics: context.metrics.clone(),
+            limits: RpcLimits {
+                max_concurrent: 1,
+                ..RpcLimits::default()
+            },
+            admin_token: None,
+        };
+        let server = RPCServer::new(limited, Ipv4Addr::LOCALHOST.into(), 0);
+        let limited_addr = server.start().await.unwrap();
+        let _ = addr;
+
+        // Occupy the only permit with a request whose body never arrives.
+        let (sender, body) = Body::channel();
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{limited_addr}/tx"))
+            .body(body)
+            .unwrap();
+        let inflight = tokio::spawn(async move {
+            let client = hyper::Client::new();
+            client.request(req).await.unwrap().status()
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let (status, body) = get_json(limited_addr, "/stats").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["message"], "server at capacity");
+
+        // Releasing the in-flight request frees the permit; the server is
+        // still up and serves normally.
+        drop(sender);
+        inflight.await.unwrap();
+        let (status, _) = get_json(limited_addr, "/stats").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn admin_token_gates_mutating_endpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, context) = start_test_server(dir.path()).await;
+        // The shared context has no token; start a second server with one.
+        let engine = context.engine.clone();
+        let secured = RpcContext {
+            consensus: engine.consensus().clone(),
+            engine,
+            mempool: context.mempool.clone(),
+            state: context.state.clone(),
+            currencies: context.currencies.clone(),
+            metrics: context.metrics.clone(),
+            limits: RpcLimits::default(),
+            admin_token: Some("s3cret".into()),
+        };
+        let server = RPCServer::new(secured, Ipv4Addr::LOCALHOST.into(), 0);
+        let secured_addr = server.start().await.unwrap();
+        let _ = addr;
+
+        let client = hyper::Client::new();
+        let body = json!({"source": "alice", "target": "bob", "amount": 5}).to_string();
+
+        // No token: 401, and nothing is created.
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{secured_addr}/create"))
+            .body(Body::from(body.clone()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Wrong token: still 401.
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{secured_addr}/create"))
+            .header("Authorization", "Bearer wrong")
+            .body(Body::from(body.clone()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Correct token: accepted.
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{secured_addr}/create"))
+            .header("Authorization", "Bearer s3cret")
+            .body(Body::from(body))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Reads stay open without a token.
+        let (status, _) = get_json(secured_addr, "/stats").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn created_vertices_reference_existing_tips_as_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, _) = start_test_server(dir.path()).await;
+        let client = hyper::Client::new();
+        let create = |nonce: u64| {
+            let client = client.clone();
+            async move {
+                let req = Request::builder()
+                    .method(Method::POST)
+                    .uri(format!("http://{addr}/create"))
+                    .body(Body::from(
+                        json!({"source": "alice", "target": "bob", "amount": 5, "nonce": nonce})
+                            .to_string(),
+                    ))
+                    .unwrap();
+                let resp = client.request(req).await.unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+                let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                value["hash"].as_str().unwrap().to_string()
+            }
+        };
+
+        // The first two vertices are roots; once two tips exist the next
+        // create references them as parents with an advanced clock.
+        let first = create(0).await;
+        let second = create(1).await;
+        let third = create(2).await;
+        let (status, body) = get_json(addr, &format!("/vertex/{third}")).await;
+        assert_eq!(status, StatusCode::OK);
+        let parents: Vec<String> = body["parents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p.as_str().unwrap().to_string())
+            .collect();
+        assert!(parents.contains(&first));
+        assert!(parents.contains(&second));
+        assert_eq!(body["logical_clock"], 1);
+    }
+
+    #[tokio::test]
+    async fn health_and_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, _) = start_test_server(dir.path()).await;
+        let (status, body) = get_json(addr, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        let (status, body) = get_json(addr, "/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_vertices"], 0);
+    }
+
+    #[tokio::test]
+    async fn options_preflight_returns_no_content_with_cors() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, _) = start_test_server(dir.path()).await;
+        let client = hyper::Client::new();
+        let req = Request::builder()
+            .method(Method::OPTIONS)
+            .uri(format!("http://{addr}/create"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let methods = resp
+            .headers()
+            .get("Access-Control-Allow-Methods")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(methods.contains("POST"));
+    }
+
+    #[tokio::test]
+    async fn oversized_body_is_rejected_with_413() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, context) = start_test_server(dir.path()).await;
+        let too_big = context.limits.max_body_bytes + 1;
+        let client = hyper::Client::new();
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{addr}/tx"))
+            .body(Body::from(vec![b'x'; too_big]))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn create_and_fetch_vertex() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, _) = start_test_server(dir.path()).await;
+        let client = hyper::Client::new();
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{addr}/create"))
+            .body(Body::from(
+                json!({"source": "alice", "target": "bob", "amount": 5}).to_string(),
+            ))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let hash = value["hash"].as_str().unwrap().to_string();
+
+        let (status, vertex) = get_json(addr, &format!("/vertex/{hash}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(vertex["source"], "alice");
+    }
+
+    #[tokio::test]
+    async fn consensus_round_can_be_driven_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, context) = start_test_server(dir.path()).await;
+        context
+            .consensus
+            .write()
+            .unwrap()
+            .add_validator(crate::consensus::ValidatorInfo::new(
+                "v0".into(),
+                100,
+                Vec::new(),
+            )).unwrap();
+        let vertex = DAGVertex::new(
+            TransactionData {
+                source: "alice".into(),
+                target: "bob".into(),
+                amount: 5,
+                currency: 1,
+                nonce: 1,
+                fee: 1,
+                user_data: Vec::new(),
+                outputs: Vec::new(),
+            },
+            Vec::new(),
+            0,
+            0,
+        );
+        context.engine.insert_vertex(vertex.clone()).unwrap();
+
+        let (status, before) = get_json(addr, "/consensus/status").await;
+        assert_eq!(status, StatusCode::OK);
+        let round_before = before["round"].as_u64().unwrap();
+
+        let client = hyper::Client::new();
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{addr}/consensus/round"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["round"].as_u64().unwrap(), round_before + 1);
+        assert_eq!(value["processed"].as_u64().unwrap(), 1);
+        assert_eq!(value["finalized"].as_u64().unwrap(), 1);
+        assert_eq!(
+            value["proofs"][0]["vertex_hash"].as_str().unwrap(),
+            hex::encode(vertex.tx_hash)
+        );
+
+        let (status, after) = get_json(addr, "/consensus/status").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(after["round"].as_u64().unwrap(), round_before + 1);
+        assert_eq!(after["validators"].as_u64().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn bulk_lookup_distinguishes_found_and_missing_vertices() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, context) = start_test_server(dir.path()).await;
+        let mut known = Vec::new();
+        for nonce in 0..2u64 {
+            let vertex = DAGVertex::new(
+                TransactionData {
+                    source: "alice".into(),
+                    target: "bob".into(),
+                    amount: 5,
+                    currency: 1,
+                    nonce,
+                    fee: 1,
+                    user_data: Vec::new(),
+                    outputs: Vec::new(),
+                },
+                Vec::new(),
+                0,
+                0,
+            );
+            context.engine.insert_vertex(vertex.clone()).unwrap();
+            known.push(vertex.tx_hash);
+        }
+
+        let client = hyper::Client::new();
+        let body = json!({
+            "hashes": [
+                hex::encode(known[0]),
+                "ff".repeat(32),
+                hex::encode(known[1]),
+            ]
+        });
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{addr}/vertices"))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let slots = value["vertices"].as_array().unwrap();
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[0]["hash"].as_str().unwrap(), hex::encode(known[0]));
+        assert!(slots[1].is_null());
+        assert_eq!(slots[2]["hash"].as_str().unwrap(), hex::encode(known[1]));
+
+        // Oversized batches are refused outright.
+        let too_many: Vec<String> = (0..=RpcLimits::default().max_batch_hashes)
+            .map(|_| "00".repeat(32))
+            .collect();
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{addr}/vertices"))
+            .body(Body::from(json!({ "hashes": too_many }).to_string()))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn recent_lists_newest_vertices_first_up_to_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, context) = start_test_server(dir.path()).await;
+        let mut hashes = Vec::new();
+        for nonce in 0..3u64 {
+            let vertex = DAGVertex::new(
+                TransactionData {
+                    source: "alice".into(),
+                    target: "bob".into(),
+                    amount: 5,
+                    currency: 1,
+                    nonce,
+                    fee: 1,
+                    user_data: Vec::new(),
+                    outputs: Vec::new(),
+                },
+                Vec::new(),
+                0,
+                0,
+            );
+            context.engine.insert_vertex(vertex.clone()).unwrap();
+            hashes.push(hex::encode(vertex.tx_hash));
+        }
+
+        let (status, body) = get_json(addr, "/recent?limit=2").await;
+        assert_eq!(status, StatusCode::OK);
+        let listing = body["vertices"].as_array().unwrap();
+        assert_eq!(listing.len(), 2);
+        assert_eq!(listing[0]["hash"].as_str().unwrap(), hashes[2]);
+        assert_eq!(listing[1]["hash"].as_str().unwrap(), hashes[1]);
+        assert_eq!(listing[0]["parent_count"].as_u64().unwrap(), 0);
+
+        let (status, body) = get_json(addr, "/recent").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["vertices"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn missing_vertex_is_404() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, _) = start_test_server(dir.path()).await;
+        let (status, _) = get_json(addr, &format!("/vertex/{}", "00".repeat(32))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn error_bodies_follow_the_standard_shape() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, _) = start_test_server(dir.path()).await;
+
+        // Missing vertex: 404 with the NotFound code.
+        let (status, body) = get_json(addr, &format!("/vertex/{}", "00".repeat(32))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], DAGErrorCode::NotFound as u32);
+        assert!(body["message"].is_string());
+
+        // Malformed hash: 400 with the ValidationError code.
+        let (status, body) = get_json(addr, "/vertex/nothex").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], DAGErrorCode::ValidationError as u32);
+
+        // Unknown route: 404 with the NotFound code.
+        let (status, body) = get_json(addr, "/no/such/route").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], DAGErrorCode::NotFound as u32);
+
+        // Invalid JSON body: 400 with the SerializationError code.
+        let client = hyper::Client::new();
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("http://{addr}/tx"))
+            .body(Body::from("{not json"))
+            .unwrap();
+        let resp = client.request(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], DAGErrorCode::SerializationError as u32);
+    }
+}
@@ -0,0 +1,69 @@
+//! Shard assignment: maps namespaces and vertex hashes onto a fixed set of
+//! hash-range shards.
+
+use sha2::{Digest, Sha256};
+
+use crate::vertex::VertexHash;
+
+/// Coordinates shard assignment over the 256-value first-byte hash space.
+#[derive(Debug, Clone)]
+pub struct ShardCoordinator {
+    shard_count: u32,
+}
+
+impl ShardCoordinator {
+    pub fn new(shard_count: u32) -> Self {
+        ShardCoordinator {
+            shard_count: shard_count.max(1),
+        }
+    }
+
+    pub fn shard_count(&self) -> u32 {
+        self.shard_count
+    }
+
+    /// Assigns a namespace (e.g. an address or CNS name) to a shard by
+    /// hashing it and routing the digest.
+    pub fn assign_shard(&self, namespace: &str) -> u32 {
+        let digest: [u8; 32] = Sha256::digest(namespace.as_bytes()).into();
+        self.find_shard_for_hash(&digest)
+    }
+
+    /// Routes a vertex hash to its shard by first byte.
+    pub fn find_shard_for_hash(&self, hash: &VertexHash) -> u32 {
+        (hash[0] as u32) * self.shard_count / 256
+    }
+
+    /// The inclusive first-byte range `[start, end]` owned by a shard.
+    pub fn shard_range(&self, shard_id: u32) -> (u8, u8) {
+        let shard_id = shard_id.min(self.shard_count - 1);
+        let start = (shard_id * 256 / self.shard_count) as u8;
+        let end = (((shard_id + 1) * 256 / self.shard_count) - 1) as u8;
+        (start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assignment_is_stable_and_in_range() {
+        let coordinator = ShardCoordinator::new(4);
+        let shard = coordinator.assign_shard("alice");
+        assert_eq!(shard, coordinator.assign_shard("alice"));
+        assert!(shard < 4);
+    }
+
+    #[test]
+    fn ranges_cover_the_space() {
+        let coordinator = ShardCoordinator::new(4);
+        assert_eq!(coordinator.shard_range(0).0, 0);
+        assert_eq!(coordinator.shard_range(3).1, 255);
+        for id in 0..3 {
+            let (_, end) = coordinator.shard_range(id);
+            let (next_start, _) = coordinator.shard_range(id + 1);
+            assert_eq!(end as u16 + 1, next_start as u16);
+        }
+    }
+}
@@ -0,0 +1,896 @@
+//! Account state machine: balances, nonces and the CNS name registry.
+//!
+//! State is updated by applying finalized vertices in the order the node
+//! observes finality events.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use log::warn;
+
+use crate::error::DAGError;
+use crate::vertex::{DAGVertex, Payload, PayloadType, TransactionData};
+
+/// Native currency identifier.
+pub const CS_CURRENCY: u32 = 1;
+
+/// Source address used by mining reward vertices.
+pub const COINBASE_SOURCE: &str = "coinbase";
+
+/// What happens to transaction fees after they are debited from the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeePolicy {
+    /// Fees are destroyed, shrinking supply.
+    Burn,
+    /// Fees are credited to the configured network-wide fee recipient.
+    /// The recipient must be part of the shared configuration: if each node
+    /// picked its own (e.g. its local wallet), replicas applying the same
+    /// finalized vertex would credit different addresses and their balances
+    /// would diverge. With no recipient configured, fees burn.
+    Reward,
+}
+
+/// Display metadata for one currency.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CurrencyInfo {
+    pub name: String,
+    pub symbol: String,
+    /// Smallest-unit decimal places used when formatting amounts.
+    pub decimals: u32,
+}
+
+/// Currency display metadata keyed by currency id.
+///
+/// CS is always present; deployments register their tokens on top so every
+/// formatter shows the right symbol and decimals instead of assuming CS.
+#[derive(Debug, Clone)]
+pub struct CurrencyRegistry {
+    currencies: HashMap<u32, CurrencyInfo>,
+}
+
+impl Default for CurrencyRegistry {
+    fn default() -> Self {
+        let mut currencies = HashMap::new();
+        currencies.insert(
+            CS_CURRENCY,
+            CurrencyInfo {
+                name: "Credits".into(),
+                symbol: "CREDITS".into(),
+                decimals: 6,
+            },
+        );
+        CurrencyRegistry { currencies }
+    }
+}
+
+impl CurrencyRegistry {
+    /// The default registry extended with the configured currencies.
+    pub fn with_currencies(extra: &[(u32, CurrencyInfo)]) -> Self {
+        let mut registry = Self::default();
+        for (id, info) in extra {
+            registry.currencies.insert(*id, info.clone());
+        }
+        registry
+    }
+
+    pub fn get(&self, currency: u32) -> Option<&CurrencyInfo> {
+        self.currencies.get(&currency)
+    }
+
+    /// Symbol for a currency; unknown ids get a generic `CUR{id}`.
+    pub fn symbol(&self, currency: u32) -> String {
+        match self.currencies.get(&currency) {
+            Some(info) => info.symbol.clone(),
+            None => format!("CUR{currency}"),
+        }
+    }
+
+    /// Formats a smallest-unit amount with the currency's decimals and
+    /// symbol; unknown currencies are shown in raw units.
+    pub fn format_amount(&self, amount: u64, currency: u32) -> String {
+        match self.currencies.get(&currency) {
+            Some(info) => format!(
+                "{:.*} {}",
+                info.decimals as usize,
+                amount as f64 / 10f64.powi(info.decimals as i32),
+                info.symbol
+            ),
+            None => format!("{amount} CUR{currency}"),
+        }
+    }
+}
+
+/// One genesis allocation: an address seeded with a starting balance.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PremineAllocation {
+    pub address: String,
+    pub currency: u32,
+    pub amount: u64,
+}
+
+/// Point-in-time supply and fee totals; see [`StateMachine::supply`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SupplySnapshot {
+    /// CS seeded by the genesis premine.
+    pub premine_supply: u64,
+    /// CS issued by coinbase rewards since genesis.
+    pub total_issued: u64,
+    /// Fees destroyed under the burn policy.
+    pub fees_burned: u64,
+    /// Fees paid out to the fee recipient under the reward policy.
+    pub fees_collected: u64,
+    /// `premine + issued - burned`; rewarded fees merely move within it.
+    pub circulating_supply: u64,
+}
+
+/// CNS name registry: name -> owning address.
+#[derive(Debug, Default)]
+pub struct CnsRegistry {
+    names: HashMap<String, String>,
+}
+
+impl CnsRegistry {
+    pub fn register(&mut self, name: String, owner: String) -> bool {
+        if self.names.contains_key(&name) {
+            return false;
+        }
+        self.names.insert(name, owner);
+        true
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<&String> {
+        self.names.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Replicated account state derived from finalized vertices.
+pub struct StateMachine {
+    /// CS balances per address.
+    state: RwLock<HashMap<String, u64>>,
+    /// Token balances per (address, currency).
+    token_balances: RwLock<HashMap<(String, u32), u64>>,
+    /// Last applied nonce per address.
+    nonces: RwLock<HashMap<String, u64>>,
+    cns: RwLock<CnsRegistry>,
+    fee_policy: FeePolicy,
+    /// Where rewarded fees go; unset means they burn regardless of policy.
+    fee_recipient: RwLock<Option<String>>,
+    /// Running totals of fees destroyed and fees paid out.
+    fees_burned: RwLock<u64>,
+    fees_collected: RwLock<u64>,
+    /// Running total of CS issued by coinbase rewards.
+    total_issued: RwLock<u64>,
+    /// Hash of the genesis premine, set by [`StateMachine::initialize_state`].
+    genesis_hash: RwLock<[u8; 32]>,
+    /// The premine itself, kept so a rebuild can replay it.
+    premine: RwLock<Vec<PremineAllocation>>,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        Self::with_fee_policy(FeePolicy::Burn)
+    }
+
+    pub fn with_fee_policy(fee_policy: FeePolicy) -> Self {
+        StateMachine {
+            state: RwLock::new(HashMap::new()),
+            token_balances: RwLock::new(HashMap::new()),
+            nonces: RwLock::new(HashMap::new()),
+            cns: RwLock::new(CnsRegistry::default()),
+            fee_policy,
+            fee_recipient: RwLock::new(None),
+            fees_burned: RwLock::new(0),
+            fees_collected: RwLock::new(0),
+            total_issued: RwLock::new(0),
+            genesis_hash: RwLock::new([0u8; 32]),
+            premine: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Sets the address rewarded fees are credited to.
+    pub fn set_fee_recipient(&self, address: String) {
+        *self.fee_recipient.write().unwrap() = Some(address);
+    }
+
+    /// Total fees destroyed so far.
+    pub fn fees_burned(&self) -> u64 {
+        *self.fees_burned.read().unwrap()
+    }
+
+    /// Total fees paid out to the fee recipient so far.
+    pub fn fees_collected(&self) -> u64 {
+        *self.fees_collected.read().unwrap()
+    }
+
+    /// Total CS issued by coinbase rewards so far.
+    pub fn total_issued(&self) -> u64 {
+        *self.total_issued.read().unwrap()
+    }
+
+    /// Economics snapshot for explorers and exchanges.
+    pub fn supply(&self) -> SupplySnapshot {
+        let premine_supply: u64 = self
+            .premine
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|alloc| alloc.currency == CS_CURRENCY)
+            .fold(0, |acc, alloc| acc.saturating_add(alloc.amount));
+        let total_issued = self.total_issued();
+        let fees_burned = self.fees_burned();
+        SupplySnapshot {
+            premine_supply,
+            total_issued,
+            fees_burned,
+            fees_collected: self.fees_collected(),
+            circulating_supply: premine_supply
+                .saturating_add(total_issued)
+                .saturating_sub(fees_burned),
+        }
+    }
+
+    /// Loads the initial state from the genesis premine and returns the
+    /// genesis hash. The hash covers every allocation in a canonical order,
+    /// so nodes configured with different premines diverge immediately and
+    /// visibly instead of during a later transfer.
+    pub fn initialize_state(
+        &self,
+        premine: &[PremineAllocation],
+    ) -> Result<[u8; 32], DAGError> {
+        use sha2::{Digest, Sha256};
+
+        let mut sorted: Vec<&PremineAllocation> = premine.iter().collect();
+        sorted.sort_by(|a, b| {
+            (&a.address, a.currency, a.amount).cmp(&(&b.address, b.currency, b.amount))
+        });
+
+        let mut hasher = Sha256::new();
+        for alloc in &sorted {
+            hasher.update(alloc.address.as_bytes());
+            hasher.update(alloc.currency.to_le_bytes());
+            hasher.update(alloc.amount.to_le_bytes());
+            self.credit_currency(&alloc.address, alloc.amount, alloc.currency);
+        }
+        let hash: [u8; 32] = hasher.finalize().into();
+        *self.genesis_hash.write().unwrap() = hash;
+        *self.premine.write().unwrap() = premine.to_vec();
+        Ok(hash)
+    }
+
+    /// Discards every balance, nonce and CNS registration and reconstructs
+    /// them by replaying the premine and then every finalized vertex in the
+    /// canonical round order, recovering from state corruption or imported
+    /// history that was never applied. Returns how many vertices were
+    /// replayed.
+    pub fn rebuild_from_dag(&self, engine: &crate::engine::DAGEngine) -> Result<u64, DAGError> {
+        self.state.write().unwrap().clear();
+        self.token_balances.write().unwrap().clear();
+        self.nonces.write().unwrap().clear();
+        *self.cns.write().unwrap() = CnsRegistry::default();
+        *self.fees_burned.write().unwrap() = 0;
+        *self.fees_collected.write().unwrap() = 0;
+        *self.total_issued.write().unwrap() = 0;
+
+        let premine = self.premine.read().unwrap().clone();
+        for alloc in &premine {
+            self.credit_currency(&alloc.address, alloc.amount, alloc.currency);
+        }
+
+        let ordered = engine.consensus().read().unwrap().finalized_order_all();
+        let mut replayed = 0;
+        for hash in ordered {
+            let Some(vertex) = engine.get_vertex(&hash)? else {
+                return Err(DAGError::NotFound(format!(
+                    "finalized vertex {} missing from storage",
+                    hex::encode(hash)
+                )));
+            };
+            if engine.is_reorged_out(&hash) {
+                continue;
+            }
+            // Mirror the live state applier: a vertex that fails to apply is
+            // skipped with a warning, not a rebuild abort.
+            if let Err(e) = self.apply_vertex(&vertex) {
+                warn!("rebuild skipped {}: {e}", hex::encode(&hash[..8]));
+                continue;
+            }
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+
+    /// Hash of the applied genesis premine; all-zero before initialization.
+    pub fn genesis_hash(&self) -> [u8; 32] {
+        *self.genesis_hash.read().unwrap()
+    }
+
+    /// CS balance of an address.
+    pub fn get_balance(&self, address: &str) -> u64 {
+        self.state.read().unwrap().get(address).copied().unwrap_or(0)
+    }
+
+    /// Balance of an address in a specific currency.
+    pub fn get_token_balance(&self, address: &str, currency: u32) -> u64 {
+        if currency == CS_CURRENCY {
+            return self.get_balance(address);
+        }
+        self.token_balances
+            .read()
+            .unwrap()
+            .get(&(address.to_string(), currency))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Last applied nonce for an address (0 if none).
+    pub fn get_nonce(&self, address: &str) -> u64 {
+        self.nonces.read().unwrap().get(address).copied().unwrap_or(0)
+    }
+
+    /// Whether the vertex payload is a CNS operation.
+    pub fn is_cns_transaction(vertex: &DAGVertex) -> bool {
+        vertex.transaction_data.payload_type() == PayloadType::Cns
+    }
+
+    /// Whether the vertex payload is an ordinal inscription.
+    pub fn is_ordinal_transaction(vertex: &DAGVertex) -> bool {
+        vertex.transaction_data.payload_type() == PayloadType::Ordinal
+    }
+
+    /// Total a transfer debits from its source per currency: the primary
+    /// amount and each output in their own currency, the fee in CS.
+    /// Rejects totals that would overflow `u64` so wrap-around amounts can
+    /// never pass a balance check.
+    pub(crate) fn debit_totals(tx: &TransactionData) -> Result<HashMap<u32, u64>, DAGError> {
+        let mut debits: HashMap<u32, u64> = HashMap::new();
+        for (amount, currency) in std::iter::once((tx.amount, tx.currency))
+            .chain(tx.outputs.iter().map(|out| (out.amount, out.currency)))
+            .chain(std::iter::once((tx.fee, CS_CURRENCY)))
+        {
+            let slot = debits.entry(currency).or_insert(0);
+            *slot = slot
+                .checked_add(amount)
+                .ok_or_else(|| Self::overflow_error(tx))?;
+        }
+        Ok(debits)
+    }
+
+    fn overflow_error(tx: &TransactionData) -> DAGError {
+        DAGError::ValidationError(format!("transfer from {} overflows u64", tx.source))
+    }
+
+    /// Applies a finalized vertex's transfer to the state.
+    pub fn apply_vertex(&self, vertex: &DAGVertex) -> Result<(), DAGError> {
+        let tx = &vertex.transaction_data;
+
+        if tx.source == COINBASE_SOURCE {
+            let mut state = self.state.write().unwrap();
+            let mut issued = self.total_issued.write().unwrap();
+            let held = state.get(&tx.target).copied().unwrap_or(0);
+            let (new_held, new_issued) = match (
+                held.checked_add(tx.amount),
+                issued.checked_add(tx.amount),
+            ) {
+                (Some(held), Some(issued)) => (held, issued),
+                _ => return Err(Self::overflow_error(tx)),
+            };
+            state.insert(tx.target.clone(), new_held);
+            *issued = new_issued;
+            return Ok(());
+        }
+
+        // Each amount is debited from the source's balance in its own
+        // currency and the fee from CS, mirroring the admission check.
+        // Every debit and credit is verified -- balance and overflow --
+        // before any is touched, so a transfer either applies in full or
+        // not at all, and amounts chosen to wrap the arithmetic are
+        // rejected instead of minting supply.
+        let debits = Self::debit_totals(tx)?;
+        let mut credits: HashMap<(&str, u32), u64> = HashMap::new();
+        for (target, amount, currency) in std::iter::once((tx.target.as_str(), tx.amount, tx.currency))
+            .chain(
+                tx.outputs
+                    .iter()
+                    .map(|out| (out.target.as_str(), out.amount, out.currency)),
+            )
+        {
+            let slot = credits.entry((target, currency)).or_insert(0);
+            *slot = slot
+                .checked_add(amount)
+                .ok_or_else(|| Self::overflow_error(tx))?;
+        }
+        {
+            let mut state = self.state.write().unwrap();
+            let mut tokens = self.token_balances.write().unwrap();
+            for (&currency, &debit) in &debits {
+                let held = if currency == CS_CURRENCY {
+                    state.get(&tx.source).copied().unwrap_or(0)
+                } else {
+                    tokens
+                        .get(&(tx.source.clone(), currency))
+                        .copied()
+                        .unwrap_or(0)
+                };
+                if held < debit {
+                    return Err(DAGError::InsufficientBalance(format!(
+                        "{} (currency {currency}): {held} < {debit}",
+                        tx.source
+                    )));
+                }
+            }
+            for (&(target, currency), &credit) in &credits {
+                let held = if currency == CS_CURRENCY {
+                    state.get(target).copied().unwrap_or(0)
+                } else {
+                    tokens
+                        .get(&(target.to_string(), currency))
+                        .copied()
+                        .unwrap_or(0)
+                };
+                if held.checked_add(credit).is_none() {
+                    return Err(Self::overflow_error(tx));
+                }
+            }
+            for (currency, debit) in debits {
+                if currency == CS_CURRENCY {
+                    *state.entry(tx.source.clone()).or_insert(0) -= debit;
+                } else {
+                    *tokens.entry((tx.source.clone(), currency)).or_insert(0) -= debit;
+                }
+            }
+            for ((target, currency), credit) in credits {
+                if currency == CS_CURRENCY {
+                    *state.entry(target.to_string()).or_insert(0) += credit;
+                } else {
+                    *tokens.entry((target.to_string(), currency)).or_insert(0) += credit;
+                }
+            }
+        }
+
+        self.settle_fee(tx.fee);
+        self.nonces
+            .write()
+            .unwrap()
+            .insert(tx.source.clone(), tx.nonce);
+
+        if Self::is_cns_transaction(vertex) {
+            self.apply_cns_operation(vertex);
+        }
+        Ok(())
+    }
+
+    /// Applies the fee policy to an already-debited fee.
+    fn settle_fee(&self, fee: u64) {
+        if fee == 0 {
+            return;
+        }
+        let recipient = self.fee_recipient.read().unwrap().clone();
+        match (self.fee_policy, recipient) {
+            (FeePolicy::Reward, Some(recipient)) => {
+                self.credit_currency(&recipient, fee, CS_CURRENCY);
+                let mut collected = self.fees_collected.write().unwrap();
+                *collected = collected.saturating_add(fee);
+            }
+            _ => {
+                let mut burned = self.fees_burned.write().unwrap();
+                *burned = burned.saturating_add(fee);
+            }
+        }
+    }
+
+    /// Saturating: the transfer path verifies its credits before applying
+    /// them, and the remaining callers (premine, fee settlement, manual
+    /// credits) are local configuration, so saturation keeps a broken
+    /// invariant from becoming a panic under overflow checks.
+    fn credit_currency(&self, address: &str, amount: u64, currency: u32) {
+        if currency == CS_CURRENCY {
+            let mut state = self.state.write().unwrap();
+            let balance = state.entry(address.to_string()).or_insert(0);
+            *balance = balance.saturating_add(amount);
+        } else {
+            let mut tokens = self.token_balances.write().unwrap();
+            let balance = tokens.entry((address.to_string(), currency)).or_insert(0);
+            *balance = balance.saturating_add(amount);
+        }
+    }
+
+    fn apply_cns_operation(&self, vertex: &DAGVertex) {
+        let Payload::Cns(op) = vertex.transaction_data.payload() else {
+            return;
+        };
+        if op.op == "reg" && !op.name.is_empty() {
+            self.cns
+                .write()
+                .unwrap()
+                .register(op.name, vertex.transaction_data.source.clone());
+        }
+    }
+
+    /// Resolves a CNS name to its owner address.
+    pub fn resolve_cns(&self, name: &str) -> Option<String> {
+        self.cns.read().unwrap().resolve(name).cloned()
+    }
+
+    /// Directly credits an address in a specific currency (used by tests).
+    pub fn credit_token(&self, address: &str, amount: u64, currency: u32) {
+        self.credit_currency(address, amount, currency);
+    }
+
+    /// Directly credits an address (used by tests and the mining path).
+    pub fn credit(&self, address: &str, amount: u64) {
+        *self
+            .state
+            .write()
+            .unwrap()
+            .entry(address.to_string())
+            .or_insert(0) += amount;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::{TransactionData, TransferOutput};
+
+    fn transfer_vertex(source: &str, target: &str, amount: u64, fee: u64, nonce: u64) -> DAGVertex {
+        let tx = TransactionData {
+            source: source.into(),
+            target: target.into(),
+            amount,
+            currency: CS_CURRENCY,
+            nonce,
+            fee,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, Vec::new(), 1, 0)
+    }
+
+    #[test]
+    fn transfer_moves_balance() {
+        let state = StateMachine::new();
+        state.credit("alice", 1_000);
+        state
+            .apply_vertex(&transfer_vertex("alice", "bob", 400, 10, 1))
+            .unwrap();
+        assert_eq!(state.get_balance("alice"), 590);
+        assert_eq!(state.get_balance("bob"), 400);
+        assert_eq!(state.get_nonce("alice"), 1);
+    }
+
+    #[test]
+    fn insufficient_balance_rejected() {
+        let state = StateMachine::new();
+        state.credit("alice", 100);
+        let result = state.apply_vertex(&transfer_vertex("alice", "bob", 400, 10, 1));
+        assert!(matches!(result, Err(DAGError::InsufficientBalance(_))));
+        assert_eq!(state.get_balance("alice"), 100);
+    }
+
+    #[test]
+    fn coinbase_credits_target() {
+        let state = StateMachine::new();
+        state
+            .apply_vertex(&transfer_vertex(COINBASE_SOURCE, "miner", 50, 0, 0))
+            .unwrap();
+        assert_eq!(state.get_balance("miner"), 50);
+    }
+
+    #[test]
+    fn coinbase_rewards_grow_the_issued_supply() {
+        let state = StateMachine::new();
+        state
+            .initialize_state(&[PremineAllocation {
+                address: "alice".into(),
+                currency: CS_CURRENCY,
+                amount: 1_000,
+            }])
+            .unwrap();
+        assert_eq!(state.supply().total_issued, 0);
+
+        for nonce in 0..3 {
+            let before = state.supply().total_issued;
+            state
+                .apply_vertex(&transfer_vertex(COINBASE_SOURCE, "miner", 50, 0, nonce))
+                .unwrap();
+            assert_eq!(state.supply().total_issued, before + 50);
+        }
+        let supply = state.supply();
+        assert_eq!(supply.premine_supply, 1_000);
+        assert_eq!(supply.circulating_supply, 1_150);
+
+        // A burned fee leaves circulation; the amount itself only moves.
+        state
+            .apply_vertex(&transfer_vertex("alice", "bob", 400, 10, 1))
+            .unwrap();
+        assert_eq!(state.supply().circulating_supply, 1_140);
+        assert_eq!(state.supply().fees_burned, 10);
+    }
+
+    #[test]
+    fn currency_registry_formats_per_currency_decimals() {
+        let registry = CurrencyRegistry::with_currencies(&[(
+            8,
+            CurrencyInfo {
+                name: "Wrapped Example".into(),
+                symbol: "WEX".into(),
+                decimals: 8,
+            },
+        )]);
+        // The same smallest-unit amount renders differently per decimals.
+        assert_eq!(registry.format_amount(1_500_000, CS_CURRENCY), "1.500000 CREDITS");
+        assert_eq!(registry.format_amount(1_500_000, 8), "0.01500000 WEX");
+        assert_eq!(registry.symbol(8), "WEX");
+        // Unknown currencies fall back to raw units.
+        assert_eq!(registry.format_amount(42, 99), "42 CUR99");
+    }
+
+    #[test]
+    fn premine_seeds_balances_and_hashes_canonically() {
+        let premine = vec![
+            PremineAllocation {
+                address: "alice".into(),
+                currency: CS_CURRENCY,
+                amount: 1_000,
+            },
+            PremineAllocation {
+                address: "bob".into(),
+                currency: 7,
+                amount: 500,
+            },
+        ];
+        let state = StateMachine::new();
+        let hash = state.initialize_state(&premine).unwrap();
+        assert_eq!(state.get_balance("alice"), 1_000);
+        assert_eq!(state.get_token_balance("bob", 7), 500);
+        assert_eq!(state.genesis_hash(), hash);
+
+        // The hash covers the allocations in canonical order, so listing
+        // them differently still yields the same genesis.
+        let reversed: Vec<PremineAllocation> = premine.into_iter().rev().collect();
+        let other = StateMachine::new();
+        assert_eq!(other.initialize_state(&reversed).unwrap(), hash);
+    }
+
+    #[test]
+    fn burn_policy_destroys_the_fee() {
+        let state = StateMachine::with_fee_policy(FeePolicy::Burn);
+        state.credit("alice", 1_000);
+        state
+            .apply_vertex(&transfer_vertex("alice", "bob", 400, 10, 1))
+            .unwrap();
+        assert_eq!(state.get_balance("alice"), 590);
+        assert_eq!(state.get_balance("bob"), 400);
+        assert_eq!(state.fees_burned(), 10);
+        assert_eq!(state.fees_collected(), 0);
+    }
+
+    #[test]
+    fn reward_policy_pays_the_fee_recipient() {
+        let state = StateMachine::with_fee_policy(FeePolicy::Reward);
+        state.set_fee_recipient("miner".into());
+        state.credit("alice", 1_000);
+        state
+            .apply_vertex(&transfer_vertex("alice", "bob", 400, 10, 1))
+            .unwrap();
+        assert_eq!(state.get_balance("alice"), 590);
+        assert_eq!(state.get_balance("bob"), 400);
+        assert_eq!(state.get_balance("miner"), 10);
+        assert_eq!(state.fees_collected(), 10);
+        assert_eq!(state.fees_burned(), 0);
+    }
+
+    #[test]
+    fn multi_output_transfer_debits_each_currency_and_credits_each_output() {
+        let state = StateMachine::new();
+        state.credit("alice", 1_000);
+        state.credit_token("alice", 300, 7);
+        let mut vertex = transfer_vertex("alice", "bob", 100, 10, 1);
+        vertex.transaction_data.outputs = vec![
+            TransferOutput {
+                target: "carol".into(),
+                amount: 200,
+                currency: CS_CURRENCY,
+            },
+            TransferOutput {
+                target: "dave".into(),
+                amount: 300,
+                currency: 7,
+            },
+        ];
+        vertex.tx_hash = vertex.calculate_hash();
+        state.apply_vertex(&vertex).unwrap();
+        // CS covers the primary transfer, the CS output and the fee; the
+        // token output comes out of alice's token holding.
+        assert_eq!(state.get_balance("alice"), 690);
+        assert_eq!(state.get_token_balance("alice", 7), 0);
+        assert_eq!(state.get_balance("bob"), 100);
+        assert_eq!(state.get_balance("carol"), 200);
+        assert_eq!(state.get_token_balance("dave", 7), 300);
+    }
+
+    #[test]
+    fn overflowing_amounts_are_rejected_before_any_balance_moves() {
+        let state = StateMachine::new();
+        state.credit("alice", 1_000);
+        // u64::MAX + fee wraps to a tiny debit if added unchecked; the
+        // checked total rejects it instead of letting the balance check
+        // pass and minting the target u64::MAX.
+        let mut vertex = transfer_vertex("alice", "bob", u64::MAX, 10, 1);
+        vertex.tx_hash = vertex.calculate_hash();
+        assert!(matches!(
+            state.apply_vertex(&vertex),
+            Err(DAGError::ValidationError(_))
+        ));
+        assert_eq!(state.get_balance("alice"), 1_000);
+        assert_eq!(state.get_balance("bob"), 0);
+    }
+
+    #[test]
+    fn token_transfer_debits_the_token_balance_and_the_fee_from_cs() {
+        let state = StateMachine::new();
+        state.credit("alice", 100);
+        state.credit_token("alice", 500, 7);
+        let mut vertex = transfer_vertex("alice", "bob", 200, 10, 1);
+        vertex.transaction_data.currency = 7;
+        vertex.tx_hash = vertex.calculate_hash();
+        state.apply_vertex(&vertex).unwrap();
+        assert_eq!(state.get_token_balance("alice", 7), 300);
+        assert_eq!(state.get_token_balance("bob", 7), 200);
+        assert_eq!(state.get_balance("alice"), 90);
+
+        // Re-sending more than the remaining holding is refused even though
+        // the CS balance would cover it; tokens cannot be minted by spending
+        // CS.
+        let mut vertex = transfer_vertex("alice", "bob", 301, 10, 2);
+        vertex.transaction_data.currency = 7;
+        vertex.tx_hash = vertex.calculate_hash();
+        let result = state.apply_vertex(&vertex);
+        assert!(matches!(result, Err(DAGError::InsufficientBalance(_))));
+        assert_eq!(state.get_token_balance("alice", 7), 300);
+        assert_eq!(state.get_balance("alice"), 90);
+    }
+
+    #[test]
+    fn multi_output_transfer_fails_atomically() {
+        let state = StateMachine::new();
+        state.credit("alice", 250);
+        let mut vertex = transfer_vertex("alice", "bob", 100, 10, 1);
+        vertex.transaction_data.outputs = vec![TransferOutput {
+            target: "carol".into(),
+            amount: 200,
+            currency: CS_CURRENCY,
+        }];
+        vertex.tx_hash = vertex.calculate_hash();
+        let result = state.apply_vertex(&vertex);
+        assert!(matches!(result, Err(DAGError::InsufficientBalance(_))));
+        assert_eq!(state.get_balance("alice"), 250);
+        assert_eq!(state.get_balance("bob"), 0);
+        assert_eq!(state.get_balance("carol"), 0);
+    }
+
+    #[test]
+    fn cns_registration_applies() {
+        let state = StateMachine::new();
+        state.credit("alice", 1_000);
+        let mut vertex = transfer_vertex("alice", "alice", 0, 10, 1);
+        vertex.transaction_data.user_data =
+            br#"{"p":"cns","op":"reg","name":"alice.cs"}"#.to_vec();
+        vertex.tx_hash = vertex.calculate_hash();
+        state.apply_vertex(&vertex).unwrap();
+        assert_eq!(state.resolve_cns("alice.cs"), Some("alice".to_string()));
+    }
+
+    #[test]
+    fn free_text_mentioning_the_cns_tag_is_not_a_cns_transaction() {
+        let state = StateMachine::new();
+        state.credit("alice", 1_000);
+        let mut vertex = transfer_vertex("alice", "bob", 10, 10, 1);
+        vertex.transaction_data.user_data =
+            br#"memo: send "p":"cns" payloads to register names"#.to_vec();
+        vertex.tx_hash = vertex.calculate_hash();
+        assert!(!StateMachine::is_cns_transaction(&vertex));
+        assert!(!StateMachine::is_ordinal_transaction(&vertex));
+        state.apply_vertex(&vertex).unwrap();
+        // The transfer applies, but no name appears.
+        assert_eq!(state.get_balance("bob"), 10);
+        assert_eq!(state.resolve_cns("names"), None);
+    }
+
+    #[test]
+    fn rebuild_from_dag_reconstructs_balances_and_names() {
+        use crate::engine::{DAGEngine, DAGEngineConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let engine = DAGEngine::new(DAGEngineConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..DAGEngineConfig::default()
+        })
+        .unwrap();
+        engine
+            .consensus()
+            .write()
+            .unwrap()
+            .add_validator(crate::consensus::ValidatorInfo::new(
+                "v0".into(),
+                100,
+                Vec::new(),
+            )).unwrap();
+
+        let state = StateMachine::new();
+        state
+            .initialize_state(&[PremineAllocation {
+                address: "alice".into(),
+                currency: CS_CURRENCY,
+                amount: 1_000_000,
+            }])
+            .unwrap();
+
+        // Two coinbase roots, then a CNS-registering transfer on top.
+        let coinbase = |target: &str, amount: u64, nonce: u64| TransactionData {
+            source: COINBASE_SOURCE.into(),
+            target: target.into(),
+            amount,
+            currency: CS_CURRENCY,
+            nonce,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let r1 = DAGVertex::new(coinbase("miner", 500, 0), vec![], 0, 0);
+        let r2 = DAGVertex::new(coinbase("miner2", 700, 1), vec![], 0, 0);
+        let transfer = TransactionData {
+            source: "alice".into(),
+            target: "bob".into(),
+            amount: 300,
+            currency: CS_CURRENCY,
+            nonce: 1,
+            fee: 100,
+            user_data: br#"{"p":"cns","op":"reg","name":"alice.cs"}"#.to_vec(),
+            outputs: Vec::new(),
+        };
+        let t1 = DAGVertex::new(transfer, vec![r1.tx_hash, r2.tx_hash], 1, 0);
+        for vertex in [&r1, &r2, &t1] {
+            engine.insert_vertex(vertex.clone()).unwrap();
+        }
+        engine.process_consensus_round().unwrap();
+
+        // Apply live, as the state applier would, and snapshot the result.
+        for hash in engine.consensus().read().unwrap().finalized_order_all() {
+            let vertex = engine.get_vertex(&hash).unwrap().unwrap();
+            state.apply_vertex(&vertex).unwrap();
+        }
+        let expected: Vec<u64> = ["alice", "bob", "miner", "miner2"]
+            .iter()
+            .map(|addr| state.get_balance(addr))
+            .collect();
+        assert_eq!(state.get_balance("alice"), 1_000_000 - 300 - 100);
+
+        // Corrupt the state, then rebuild it from the DAG.
+        state.credit("mallory", 5);
+        let replayed = state.rebuild_from_dag(&engine).unwrap();
+        assert_eq!(replayed, 3);
+        let rebuilt: Vec<u64> = ["alice", "bob", "miner", "miner2"]
+            .iter()
+            .map(|addr| state.get_balance(addr))
+            .collect();
+        assert_eq!(rebuilt, expected);
+        assert_eq!(state.get_balance("mallory"), 0);
+        assert_eq!(state.resolve_cns("alice.cs").unwrap(), "alice");
+        assert_eq!(state.get_nonce("alice"), 1);
+    }
+}
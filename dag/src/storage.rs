@@ -0,0 +1,150 @@
+//! In-memory index structures and statistics shared by the storage backends.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::vertex::{DAGVertex, VertexHash};
+
+/// Aggregate storage statistics, snapshotted by [`get_stats`].
+///
+/// [`get_stats`]: crate::storage_unified::DAGVertexStore::get_stats
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub total_vertices: u64,
+    /// Sum of uncompressed (bincode) vertex sizes.
+    pub logical_size_bytes: u64,
+    /// Sum of vertex body bytes actually handed to the backend, after any
+    /// compression.
+    pub physical_size_bytes: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub read_operations: u64,
+    pub write_operations: u64,
+}
+
+/// Width of a time-index bucket in milliseconds. Bucketing keeps the index
+/// small under bursts of vertices sharing the same wall-clock instant.
+pub const TIME_BUCKET_MS: u64 = 1_000;
+
+/// A stable position in a `(clock, hash)`-ordered vertex listing.
+///
+/// Range queries return the cursor of the last vertex in a page; passing it
+/// back resumes after that vertex, so callers can page deterministically even
+/// as new vertices arrive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub clock: u64,
+    pub hash: VertexHash,
+}
+
+/// Secondary indices kept in memory for fast lookups.
+///
+/// These are rebuilt from the backing store on startup via
+/// [`DAGIndices::rebuild`].
+#[derive(Debug, Default)]
+pub struct DAGIndices {
+    /// logical_clock -> vertices at that clock, kept hash-sorted so every
+    /// node iterates same-clock vertices in the same order.
+    pub clock_index: HashMap<u64, Vec<VertexHash>>,
+    /// shard_id -> vertices in that shard.
+    pub shard_index: HashMap<u32, Vec<VertexHash>>,
+    /// vertex -> children that reference it as a parent.
+    pub children: HashMap<VertexHash, Vec<VertexHash>>,
+    /// vertex -> number of children; a count of 0 marks a tip.
+    pub child_count: HashMap<VertexHash, u32>,
+    /// address -> vertices whose transaction touches it as source or target.
+    pub address_index: HashMap<String, Vec<VertexHash>>,
+    /// timestamp bucket ([`TIME_BUCKET_MS`]-wide) -> vertices in that bucket,
+    /// ordered for time-range queries.
+    pub time_index: BTreeMap<u64, Vec<VertexHash>>,
+}
+
+impl DAGIndices {
+    /// Registers a vertex in all indices.
+    pub fn index_vertex(&mut self, vertex: &DAGVertex) {
+        let bucket = self.clock_index.entry(vertex.logical_clock).or_default();
+        if let Err(pos) = bucket.binary_search(&vertex.tx_hash) {
+            bucket.insert(pos, vertex.tx_hash);
+        }
+        self.shard_index
+            .entry(vertex.shard_id)
+            .or_default()
+            .push(vertex.tx_hash);
+        self.child_count.entry(vertex.tx_hash).or_insert(0);
+        for parent in &vertex.parents {
+            self.children.entry(*parent).or_default().push(vertex.tx_hash);
+            *self.child_count.entry(*parent).or_insert(0) += 1;
+        }
+        self.time_index
+            .entry(vertex.timestamp / TIME_BUCKET_MS)
+            .or_default()
+            .push(vertex.tx_hash);
+        let tx = &vertex.transaction_data;
+        self.address_index
+            .entry(tx.source.clone())
+            .or_default()
+            .push(vertex.tx_hash);
+        if tx.target != tx.source {
+            self.address_index
+                .entry(tx.target.clone())
+                .or_default()
+                .push(vertex.tx_hash);
+        }
+    }
+
+    /// Clears and rebuilds all indices from a full vertex iterator.
+    pub fn rebuild<'a>(&mut self, vertices: impl Iterator<Item = &'a DAGVertex>) {
+        self.clock_index.clear();
+        self.shard_index.clear();
+        self.children.clear();
+        self.child_count.clear();
+        self.address_index.clear();
+        self.time_index.clear();
+        for vertex in vertices {
+            self.index_vertex(vertex);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::TransactionData;
+
+    fn vertex_with(clock: u64, shard: u32, parents: Vec<VertexHash>) -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: clock,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, parents, clock, shard)
+    }
+
+    #[test]
+    fn rebuild_matches_incremental() {
+        let genesis = vertex_with(0, 0, vec![]);
+        let child = vertex_with(1, 1, vec![genesis.tx_hash]);
+
+        let mut incremental = DAGIndices::default();
+        incremental.index_vertex(&genesis);
+        incremental.index_vertex(&child);
+
+        let mut rebuilt = DAGIndices::default();
+        rebuilt.rebuild([&genesis, &child].into_iter());
+
+        assert_eq!(
+            incremental.children.get(&genesis.tx_hash),
+            rebuilt.children.get(&genesis.tx_hash)
+        );
+        assert_eq!(incremental.clock_index.len(), rebuilt.clock_index.len());
+        assert_eq!(incremental.shard_index.len(), rebuilt.shard_index.len());
+        assert_eq!(incremental.child_count, rebuilt.child_count);
+        assert_eq!(incremental.time_index, rebuilt.time_index);
+    }
+}
@@ -0,0 +1,291 @@
+//! Low-level LSM-tree vertex store backed by sled.
+
+use std::path::Path;
+
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
+
+use crate::error::DAGError;
+use crate::vertex::{DAGVertex, VertexHash};
+
+/// Magic prefix marking a zstd-compressed vertex body; bodies without it
+/// are read as plain versioned bytes, so stores written before compression
+/// was enabled stay readable.
+const COMPRESSED_MAGIC: &[u8; 4] = b"DAGZ";
+
+/// zstd level used for vertex bodies; 3 is the balanced default.
+const COMPRESSION_LEVEL: i32 = 3;
+
+fn shard_key(shard_id: u32, hash: &VertexHash) -> Vec<u8> {
+    let mut key = Vec::with_capacity(4 + 32);
+    key.extend_from_slice(&shard_id.to_be_bytes());
+    key.extend_from_slice(hash);
+    key
+}
+
+/// Persistent vertex store over a sled database.
+///
+/// Four trees are maintained: the vertex bodies, the parent lists, the
+/// children lists and a shard membership index.
+pub struct LsmVertexStore {
+    db: sled::Db,
+    vertices_tree: sled::Tree,
+    parents_tree: sled::Tree,
+    children_tree: sled::Tree,
+    shard_tree: sled::Tree,
+    /// Compress vertex bodies with zstd before writing.
+    compress: bool,
+}
+
+impl LsmVertexStore {
+    pub fn open(path: &Path, compress: bool) -> Result<Self, DAGError> {
+        let db = sled::open(path).map_err(|e| DAGError::StorageError(e.to_string()))?;
+        let vertices_tree = db
+            .open_tree("vertices")
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        let parents_tree = db
+            .open_tree("parents")
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        let children_tree = db
+            .open_tree("children")
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        let shard_tree = db
+            .open_tree("shards")
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        Ok(LsmVertexStore {
+            db,
+            vertices_tree,
+            parents_tree,
+            children_tree,
+            shard_tree,
+            compress,
+        })
+    }
+
+    /// Encodes a vertex body for storage, compressing when enabled.
+    fn encode_body(&self, vertex: &DAGVertex) -> Result<Vec<u8>, DAGError> {
+        let raw = vertex.to_versioned_bytes()?;
+        if !self.compress {
+            return Ok(raw);
+        }
+        let mut body = COMPRESSED_MAGIC.to_vec();
+        body.extend(
+            zstd::encode_all(raw.as_slice(), COMPRESSION_LEVEL)
+                .map_err(|e| DAGError::StorageError(format!("compression failed: {e}")))?,
+        );
+        Ok(body)
+    }
+
+    /// Decodes a stored vertex body, compressed or plain.
+    fn decode_body(bytes: &[u8]) -> Result<DAGVertex, DAGError> {
+        match bytes.strip_prefix(COMPRESSED_MAGIC) {
+            Some(compressed) => {
+                let raw = zstd::decode_all(compressed)
+                    .map_err(|e| DAGError::StorageError(format!("decompression failed: {e}")))?;
+                DAGVertex::from_versioned_bytes(&raw)
+            }
+            None => DAGVertex::from_versioned_bytes(bytes),
+        }
+    }
+
+    /// Writes a vertex and all of its index entries in one transaction, so a
+    /// crash can never leave the body and the indices inconsistent. Returns
+    /// the body bytes written, after compression.
+    pub fn store_vertex(&self, vertex: &DAGVertex) -> Result<u64, DAGError> {
+        let body = self.encode_body(vertex)?;
+        let parents = bincode::serialize(&vertex.parents)?;
+
+        (
+            &self.vertices_tree,
+            &self.parents_tree,
+            &self.children_tree,
+            &self.shard_tree,
+        )
+            .transaction(|(vertices, parent_lists, children, shards)| {
+                vertices.insert(&vertex.tx_hash, body.clone())?;
+                parent_lists.insert(&vertex.tx_hash, parents.clone())?;
+                for parent in &vertex.parents {
+                    let mut list: Vec<VertexHash> = match children.get(parent)? {
+                        Some(bytes) => bincode::deserialize(&bytes).map_err(|e| {
+                            ConflictableTransactionError::Abort(DAGError::from(e))
+                        })?,
+                        None => Vec::new(),
+                    };
+                    if !list.contains(&vertex.tx_hash) {
+                        list.push(vertex.tx_hash);
+                    }
+                    let encoded = bincode::serialize(&list).map_err(|e| {
+                        ConflictableTransactionError::Abort(DAGError::from(e))
+                    })?;
+                    children.insert(&parent[..], encoded)?;
+                }
+                shards.insert(shard_key(vertex.shard_id, &vertex.tx_hash), vec![])?;
+                Ok(())
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => DAGError::StorageError(e.to_string()),
+            })?;
+        self.db
+            .flush()
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        Ok(body.len() as u64)
+    }
+
+    pub fn get_vertex(&self, hash: &VertexHash) -> Result<Option<DAGVertex>, DAGError> {
+        match self
+            .vertices_tree
+            .get(hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Ok(Some(Self::decode_body(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn contains(&self, hash: &VertexHash) -> Result<bool, DAGError> {
+        self.vertices_tree
+            .contains_key(hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))
+    }
+
+    pub fn get_parents(&self, hash: &VertexHash) -> Result<Vec<VertexHash>, DAGError> {
+        match self
+            .parents_tree
+            .get(hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn get_children(&self, hash: &VertexHash) -> Result<Vec<VertexHash>, DAGError> {
+        match self
+            .children_tree
+            .get(hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns up to `limit` full vertices belonging to a shard, using the
+    /// shard index prefix scan.
+    pub fn get_vertices_by_shard(
+        &self,
+        shard_id: u32,
+        limit: usize,
+    ) -> Result<Vec<DAGVertex>, DAGError> {
+        let mut vertices = Vec::new();
+        for entry in self.shard_tree.scan_prefix(shard_id.to_be_bytes()) {
+            if vertices.len() >= limit {
+                break;
+            }
+            let (key, _) = entry.map_err(|e| DAGError::StorageError(e.to_string()))?;
+            let hash: VertexHash = key[4..]
+                .try_into()
+                .map_err(|_| DAGError::StorageError("corrupt shard index key".into()))?;
+            if let Some(vertex) = self.get_vertex(&hash)? {
+                vertices.push(vertex);
+            }
+        }
+        Ok(vertices)
+    }
+
+    /// Iterates all stored vertices (used for index rebuilds).
+    pub fn all_vertices(&self) -> Result<Vec<DAGVertex>, DAGError> {
+        let mut vertices = Vec::new();
+        for entry in self.vertices_tree.iter() {
+            let (_, bytes) = entry.map_err(|e| DAGError::StorageError(e.to_string()))?;
+            vertices.push(Self::decode_body(&bytes)?);
+        }
+        Ok(vertices)
+    }
+
+    pub fn vertex_count(&self) -> u64 {
+        self.vertices_tree.len() as u64
+    }
+
+    /// Syncs pending writes to disk, returning the bytes flushed.
+    pub fn flush(&self) -> Result<u64, DAGError> {
+        let bytes = self
+            .db
+            .flush()
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        Ok(bytes as u64)
+    }
+
+    /// Proactively compacts the database and returns the on-disk size after.
+    pub fn compact(&self) -> Result<u64, DAGError> {
+        self.db
+            .flush()
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        self.db
+            .size_on_disk()
+            .map_err(|e| DAGError::StorageError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::TransactionData;
+
+    fn sample_vertex(nonce: u64, shard: u32) -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 10,
+            currency: 1,
+            nonce,
+            fee: 1,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, Vec::new(), nonce, shard)
+    }
+
+    #[test]
+    fn store_and_reload_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LsmVertexStore::open(dir.path(), false).unwrap();
+        let vertex = sample_vertex(1, 0);
+        store.store_vertex(&vertex).unwrap();
+        let loaded = store.get_vertex(&vertex.tx_hash).unwrap().unwrap();
+        assert_eq!(loaded.tx_hash, vertex.tx_hash);
+        assert_eq!(loaded.transaction_data, vertex.transaction_data);
+    }
+
+    #[test]
+    fn aborted_transaction_leaves_no_partial_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LsmVertexStore::open(dir.path(), false).unwrap();
+        let vertex = sample_vertex(1, 0);
+        let body = bincode::serialize(&vertex).unwrap();
+
+        // Insert the body, then abort: nothing may remain visible.
+        let result: Result<(), TransactionError<()>> =
+            (&store.vertices_tree, &store.shard_tree).transaction(|(vertices, shards)| {
+                vertices.insert(&vertex.tx_hash, body.clone())?;
+                shards.insert(shard_key(vertex.shard_id, &vertex.tx_hash), vec![])?;
+                Err(ConflictableTransactionError::Abort(()))
+            });
+        assert!(result.is_err());
+        assert!(!store.contains(&vertex.tx_hash).unwrap());
+        assert_eq!(store.get_vertices_by_shard(0, 10).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn shard_scan_filters_by_shard() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LsmVertexStore::open(dir.path(), false).unwrap();
+        store.store_vertex(&sample_vertex(1, 0)).unwrap();
+        store.store_vertex(&sample_vertex(2, 1)).unwrap();
+        store.store_vertex(&sample_vertex(3, 1)).unwrap();
+        let shard1 = store.get_vertices_by_shard(1, 100).unwrap();
+        assert_eq!(shard1.len(), 2);
+        assert!(shard1.iter().all(|v| v.shard_id == 1));
+    }
+}
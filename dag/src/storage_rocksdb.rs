@@ -0,0 +1,235 @@
+//! Vertex store backed by RocksDB column families.
+//!
+//! Mirrors the layout of the sled store: vertex bodies, parent lists,
+//! children lists and a shard membership index, each in its own column
+//! family.
+
+use std::path::Path;
+
+use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, Direction, IteratorMode, Options, DB};
+
+use crate::error::DAGError;
+use crate::vertex::{DAGVertex, VertexHash};
+
+const CF_VERTICES: &str = "vertices";
+const CF_PARENTS: &str = "parents";
+const CF_CHILDREN: &str = "children";
+const CF_SHARDS: &str = "shards";
+
+fn shard_key(shard_id: u32, hash: &VertexHash) -> Vec<u8> {
+    let mut key = Vec::with_capacity(4 + 32);
+    key.extend_from_slice(&shard_id.to_be_bytes());
+    key.extend_from_slice(hash);
+    key
+}
+
+/// Persistent vertex store over a RocksDB database.
+pub struct RocksVertexStore {
+    db: DB,
+}
+
+impl RocksVertexStore {
+    pub fn open(path: &Path) -> Result<Self, DAGError> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let cfs = [CF_VERTICES, CF_PARENTS, CF_CHILDREN, CF_SHARDS]
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+        let db = DB::open_cf_descriptors(&opts, path, cfs)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        Ok(RocksVertexStore { db })
+    }
+
+    fn cf(&self, name: &str) -> Result<&ColumnFamily, DAGError> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| DAGError::StorageError(format!("missing column family {name}")))
+    }
+
+    /// Writes a vertex and all of its index entries.
+    /// Returns the body bytes handed to RocksDB; any block compression it
+    /// applies beneath is not reflected here.
+    pub fn store_vertex(&self, vertex: &DAGVertex) -> Result<u64, DAGError> {
+        let body = vertex.to_versioned_bytes()?;
+        let body_len = body.len() as u64;
+        let parents = bincode::serialize(&vertex.parents)?;
+
+        self.db
+            .put_cf(self.cf(CF_VERTICES)?, vertex.tx_hash, body)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        self.db
+            .put_cf(self.cf(CF_PARENTS)?, vertex.tx_hash, parents)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        for parent in &vertex.parents {
+            let mut children = self.get_children(parent)?;
+            if !children.contains(&vertex.tx_hash) {
+                children.push(vertex.tx_hash);
+            }
+            self.db
+                .put_cf(self.cf(CF_CHILDREN)?, parent, bincode::serialize(&children)?)
+                .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        }
+        self.db
+            .put_cf(
+                self.cf(CF_SHARDS)?,
+                shard_key(vertex.shard_id, &vertex.tx_hash),
+                [],
+            )
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        Ok(body_len)
+    }
+
+    pub fn get_vertex(&self, hash: &VertexHash) -> Result<Option<DAGVertex>, DAGError> {
+        match self
+            .db
+            .get_cf(self.cf(CF_VERTICES)?, hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Ok(Some(DAGVertex::from_versioned_bytes(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn contains(&self, hash: &VertexHash) -> Result<bool, DAGError> {
+        Ok(self
+            .db
+            .get_cf(self.cf(CF_VERTICES)?, hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+            .is_some())
+    }
+
+    pub fn get_parents(&self, hash: &VertexHash) -> Result<Vec<VertexHash>, DAGError> {
+        match self
+            .db
+            .get_cf(self.cf(CF_PARENTS)?, hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn get_children(&self, hash: &VertexHash) -> Result<Vec<VertexHash>, DAGError> {
+        match self
+            .db
+            .get_cf(self.cf(CF_CHILDREN)?, hash)
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns up to `limit` full vertices belonging to a shard, scanning the
+    /// shard index from the shard's key prefix.
+    pub fn get_vertices_by_shard(
+        &self,
+        shard_id: u32,
+        limit: usize,
+    ) -> Result<Vec<DAGVertex>, DAGError> {
+        let prefix = shard_id.to_be_bytes();
+        let mut vertices = Vec::new();
+        let iter = self.db.iterator_cf(
+            self.cf(CF_SHARDS)?,
+            IteratorMode::From(&prefix, Direction::Forward),
+        );
+        for entry in iter {
+            if vertices.len() >= limit {
+                break;
+            }
+            let (key, _) = entry.map_err(|e| DAGError::StorageError(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let hash: VertexHash = key[4..]
+                .try_into()
+                .map_err(|_| DAGError::StorageError("corrupt shard index key".into()))?;
+            if let Some(vertex) = self.get_vertex(&hash)? {
+                vertices.push(vertex);
+            }
+        }
+        Ok(vertices)
+    }
+
+    /// Iterates all stored vertices (used for index rebuilds).
+    pub fn all_vertices(&self) -> Result<Vec<DAGVertex>, DAGError> {
+        let mut vertices = Vec::new();
+        for entry in self.db.iterator_cf(self.cf(CF_VERTICES)?, IteratorMode::Start) {
+            let (_, bytes) = entry.map_err(|e| DAGError::StorageError(e.to_string()))?;
+            vertices.push(DAGVertex::from_versioned_bytes(&bytes)?);
+        }
+        Ok(vertices)
+    }
+
+    pub fn vertex_count(&self) -> u64 {
+        match self.cf(CF_VERTICES) {
+            Ok(cf) => self.db.iterator_cf(cf, IteratorMode::Start).count() as u64,
+            Err(_) => 0,
+        }
+    }
+
+    /// Syncs pending writes to disk. RocksDB does not report a flushed byte
+    /// count, so this returns 0 on success.
+    pub fn flush(&self) -> Result<u64, DAGError> {
+        self.db
+            .flush()
+            .map_err(|e| DAGError::StorageError(e.to_string()))?;
+        Ok(0)
+    }
+
+    /// Proactively compacts the database and returns the on-disk size after.
+    pub fn compact(&self) -> Result<u64, DAGError> {
+        self.db
+            .compact_range_cf::<&[u8], &[u8]>(self.cf(CF_VERTICES)?, None, None);
+        let size = self
+            .db
+            .property_int_value("rocksdb.total-sst-files-size")
+            .map_err(|e| DAGError::StorageError(e.to_string()))?
+            .unwrap_or(0);
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::TransactionData;
+
+    fn sample_vertex(nonce: u64, shard: u32) -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 10,
+            currency: 1,
+            nonce,
+            fee: 1,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, Vec::new(), nonce, shard)
+    }
+
+    #[test]
+    fn store_and_reload_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksVertexStore::open(dir.path()).unwrap();
+        let vertex = sample_vertex(1, 0);
+        store.store_vertex(&vertex).unwrap();
+        let loaded = store.get_vertex(&vertex.tx_hash).unwrap().unwrap();
+        assert_eq!(loaded.tx_hash, vertex.tx_hash);
+        assert_eq!(loaded.transaction_data, vertex.transaction_data);
+    }
+
+    #[test]
+    fn shard_scan_filters_by_shard() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksVertexStore::open(dir.path()).unwrap();
+        store.store_vertex(&sample_vertex(1, 0)).unwrap();
+        store.store_vertex(&sample_vertex(2, 1)).unwrap();
+        store.store_vertex(&sample_vertex(3, 1)).unwrap();
+        let shard1 = store.get_vertices_by_shard(1, 100).unwrap();
+        assert_eq!(shard1.len(), 2);
+        assert!(shard1.iter().all(|v| v.shard_id == 1));
+    }
+}
@@ -0,0 +1,1174 @@
+//! Unified vertex store: a caching, index-maintaining facade over a
+//! runtime-selected storage backend.
+//!
+//! Which backends exist is still a compile-time question (the `sled-backend`
+//! and `rocksdb-backend` features), but a single binary compiled with both
+//! picks between them at runtime via [`StorageBackend`].
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::error::DAGError;
+use crate::storage::{Cursor, DAGIndices, StorageStats, TIME_BUCKET_MS};
+#[cfg(feature = "sled-backend")]
+use crate::storage_lsm::LsmVertexStore;
+#[cfg(feature = "rocksdb-backend")]
+use crate::storage_rocksdb::RocksVertexStore;
+use crate::vertex::{DAGVertex, VertexHash};
+
+/// Runtime storage backend selection. Selecting a backend whose feature was
+/// not compiled in makes [`DAGVertexStore::new`] fail with a clear error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Sled,
+    RocksDb,
+    /// Non-persistent map, for tests and feature-less builds.
+    Memory,
+}
+
+impl Default for StorageBackend {
+    fn default() -> Self {
+        #[cfg(feature = "sled-backend")]
+        return StorageBackend::Sled;
+        #[cfg(all(feature = "rocksdb-backend", not(feature = "sled-backend")))]
+        return StorageBackend::RocksDb;
+        #[cfg(not(any(feature = "sled-backend", feature = "rocksdb-backend")))]
+        StorageBackend::Memory
+    }
+}
+
+/// Hot-path statistics counters, bumped with relaxed atomics so concurrent
+/// reads never serialize on a stats lock.
+#[derive(Default)]
+struct AtomicStorageStats {
+    total_vertices: AtomicU64,
+    logical_size_bytes: AtomicU64,
+    physical_size_bytes: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    read_operations: AtomicU64,
+    write_operations: AtomicU64,
+}
+
+enum Backend {
+    #[cfg(feature = "sled-backend")]
+    Sled(LsmVertexStore),
+    #[cfg(feature = "rocksdb-backend")]
+    RocksDb(RocksVertexStore),
+    Memory(RwLock<HashMap<VertexHash, DAGVertex>>),
+}
+
+impl Backend {
+    fn open(path: &Path, kind: StorageBackend, compression: bool) -> Result<Self, DAGError> {
+        match kind {
+            StorageBackend::Sled => {
+                #[cfg(feature = "sled-backend")]
+                {
+                    Ok(Backend::Sled(LsmVertexStore::open(path, compression)?))
+                }
+                #[cfg(not(feature = "sled-backend"))]
+                {
+                    Err(DAGError::StorageError(
+                        "sled backend selected but the sled-backend feature is not compiled in"
+                            .into(),
+                    ))
+                }
+            }
+            StorageBackend::RocksDb => {
+                #[cfg(feature = "rocksdb-backend")]
+                {
+                    Ok(Backend::RocksDb(RocksVertexStore::open(path)?))
+                }
+                #[cfg(not(feature = "rocksdb-backend"))]
+                {
+                    Err(DAGError::StorageError(
+                        "rocksdb backend selected but the rocksdb-backend feature is not compiled in"
+                            .into(),
+                    ))
+                }
+            }
+            StorageBackend::Memory => {
+                let _ = (path, compression);
+                Ok(Backend::Memory(RwLock::new(HashMap::new())))
+            }
+        }
+    }
+
+    /// Stores a vertex, returning the body bytes written post-compression.
+    fn store_vertex(&self, vertex: &DAGVertex) -> Result<u64, DAGError> {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.store_vertex(vertex),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.store_vertex(vertex),
+            Backend::Memory(map) => {
+                map.write().unwrap().insert(vertex.tx_hash, vertex.clone());
+                Ok(vertex.serialized_size())
+            }
+        }
+    }
+
+    fn get_vertex(&self, hash: &VertexHash) -> Result<Option<DAGVertex>, DAGError> {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.get_vertex(hash),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.get_vertex(hash),
+            Backend::Memory(map) => Ok(map.read().unwrap().get(hash).cloned()),
+        }
+    }
+
+    fn contains(&self, hash: &VertexHash) -> Result<bool, DAGError> {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.contains(hash),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.contains(hash),
+            Backend::Memory(map) => Ok(map.read().unwrap().contains_key(hash)),
+        }
+    }
+
+    fn all_vertices(&self) -> Result<Vec<DAGVertex>, DAGError> {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.all_vertices(),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.all_vertices(),
+            Backend::Memory(map) => Ok(map.read().unwrap().values().cloned().collect()),
+        }
+    }
+
+    fn vertex_count(&self) -> u64 {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.vertex_count(),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.vertex_count(),
+            Backend::Memory(map) => map.read().unwrap().len() as u64,
+        }
+    }
+
+    fn flush(&self) -> Result<u64, DAGError> {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.flush(),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.flush(),
+            Backend::Memory(_) => Ok(0),
+        }
+    }
+
+    fn compact(&self) -> Result<u64, DAGError> {
+        match self {
+            #[cfg(feature = "sled-backend")]
+            Backend::Sled(store) => store.compact(),
+            #[cfg(feature = "rocksdb-backend")]
+            Backend::RocksDb(store) => store.compact(),
+            Backend::Memory(_) => Ok(0),
+        }
+    }
+}
+
+/// Maps an inclusive range of shard ids to a dedicated storage path, so
+/// large deployments can spread shards across disks.
+#[derive(Debug, Clone)]
+pub struct ShardPartition {
+    /// Shard ids served by this partition.
+    pub shards: std::ops::RangeInclusive<u32>,
+    /// Storage directory for the partition, opened with the same backend
+    /// as the main store.
+    pub path: std::path::PathBuf,
+}
+
+/// Vertices between progress log lines during an index rebuild.
+const REBUILD_PROGRESS_INTERVAL: usize = 50_000;
+
+/// Unified vertex store used by the engine.
+pub struct DAGVertexStore {
+    /// Backend for shards without a dedicated partition.
+    backend: Backend,
+    /// Shard-range partitions, consulted before falling back to `backend`.
+    partitions: Vec<(std::ops::RangeInclusive<u32>, Backend)>,
+    indices: RwLock<DAGIndices>,
+    stats: AtomicStorageStats,
+    vertex_cache: RwLock<HashMap<VertexHash, DAGVertex>>,
+    cache_size: usize,
+    /// Test hook: when set, writes and flushes fail as if the disk were full.
+    #[cfg(test)]
+    pub(crate) fail_writes: std::sync::atomic::AtomicBool,
+}
+
+impl DAGVertexStore {
+    /// Opens the store at `path` with the given vertex cache capacity, using
+    /// the selected backend. All shards share the one backend.
+    pub fn new(
+        path: &Path,
+        cache_size: usize,
+        backend: StorageBackend,
+    ) -> Result<Self, DAGError> {
+        Self::new_partitioned(path, cache_size, backend, &[], false)
+    }
+
+    /// Like [`Self::new`], but shards covered by a [`ShardPartition`] are
+    /// persisted under that partition's path instead of `path`. Indices and
+    /// the cache stay unified, so cross-shard queries work unchanged; only
+    /// the raw vertex bytes are routed.
+    pub fn new_partitioned(
+        path: &Path,
+        cache_size: usize,
+        backend: StorageBackend,
+        partitions: &[ShardPartition],
+        compression: bool,
+    ) -> Result<Self, DAGError> {
+        let mut opened = Vec::with_capacity(partitions.len());
+        for partition in partitions {
+            opened.push((
+                partition.shards.clone(),
+                Backend::open(&partition.path, backend, compression)?,
+            ));
+        }
+        let store = DAGVertexStore {
+            backend: Backend::open(path, backend, compression)?,
+            partitions: opened,
+            indices: RwLock::new(DAGIndices::default()),
+            stats: AtomicStorageStats::default(),
+            vertex_cache: RwLock::new(HashMap::new()),
+            cache_size,
+            #[cfg(test)]
+            fail_writes: std::sync::atomic::AtomicBool::new(false),
+        };
+        store.rebuild_indices()?;
+        Ok(store)
+    }
+
+    /// Backend holding vertices of `shard_id`: the first partition covering
+    /// it, or the main backend.
+    fn backend_for_shard(&self, shard_id: u32) -> &Backend {
+        self.partitions
+            .iter()
+            .find(|(shards, _)| shards.contains(&shard_id))
+            .map(|(_, backend)| backend)
+            .unwrap_or(&self.backend)
+    }
+
+    /// Main backend plus every partition, for fan-out reads and flushes.
+    fn all_backends(&self) -> impl Iterator<Item = &Backend> {
+        std::iter::once(&self.backend).chain(self.partitions.iter().map(|(_, b)| b))
+    }
+
+    fn increment_cache_hits(&self) {
+        self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn increment_cache_misses(&self) {
+        self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn increment_read_operations(&self) {
+        self.stats.read_operations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Stores a vertex, updating indices, cache and statistics.
+    pub fn store_vertex(&self, vertex: &DAGVertex) -> Result<(), DAGError> {
+        #[cfg(test)]
+        if self.fail_writes.load(Ordering::Relaxed) {
+            return Err(DAGError::StorageError(
+                "no space left on device (injected)".into(),
+            ));
+        }
+        let physical = self.backend_for_shard(vertex.shard_id).store_vertex(vertex)?;
+
+        self.indices.write().unwrap().index_vertex(vertex);
+        {
+            let mut cache = self.vertex_cache.write().unwrap();
+            if cache.len() >= self.cache_size {
+                if let Some(evict) = cache.keys().next().copied() {
+                    cache.remove(&evict);
+                }
+            }
+            cache.insert(vertex.tx_hash, vertex.clone());
+        }
+        self.stats.total_vertices.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .logical_size_bytes
+            .fetch_add(vertex.serialized_size(), Ordering::Relaxed);
+        self.stats
+            .physical_size_bytes
+            .fetch_add(physical, Ordering::Relaxed);
+        self.stats.write_operations.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Fetches a vertex, consulting the cache first.
+    pub fn get_vertex(&self, hash: &VertexHash) -> Result<Option<DAGVertex>, DAGError> {
+        self.increment_read_operations();
+        if let Some(vertex) = self.vertex_cache.read().unwrap().get(hash) {
+            self.increment_cache_hits();
+            return Ok(Some(vertex.clone()));
+        }
+        self.increment_cache_misses();
+        let loaded = self.load_vertex(hash)?;
+        if let Some(vertex) = &loaded {
+            let mut cache = self.vertex_cache.write().unwrap();
+            if cache.len() < self.cache_size {
+                cache.insert(*hash, vertex.clone());
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Fetches many vertices in one call; each slot mirrors [`Self::get_vertex`]
+    /// for the hash at the same index, with `None` marking a miss.
+    pub fn get_vertices(&self, hashes: &[VertexHash]) -> Result<Vec<Option<DAGVertex>>, DAGError> {
+        let mut out = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            out.push(self.get_vertex(hash)?);
+        }
+        Ok(out)
+    }
+
+    fn load_vertex(&self, hash: &VertexHash) -> Result<Option<DAGVertex>, DAGError> {
+        for backend in self.all_backends() {
+            if let Some(vertex) = backend.get_vertex(hash)? {
+                return Ok(Some(vertex));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn contains(&self, hash: &VertexHash) -> Result<bool, DAGError> {
+        if self.vertex_cache.read().unwrap().contains_key(hash) {
+            return Ok(true);
+        }
+        for backend in self.all_backends() {
+            if backend.contains(hash)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn get_parents(&self, hash: &VertexHash) -> Result<Vec<VertexHash>, DAGError> {
+        Ok(self
+            .get_vertex(hash)?
+            .map(|v| v.parents)
+            .unwrap_or_default())
+    }
+
+    pub fn get_children(&self, hash: &VertexHash) -> Result<Vec<VertexHash>, DAGError> {
+        Ok(self
+            .indices
+            .read()
+            .unwrap()
+            .children
+            .get(hash)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Hashes of all vertices assigned to a shard.
+    pub fn get_shard_vertices(&self, shard_id: u32) -> Vec<VertexHash> {
+        self.indices
+            .read()
+            .unwrap()
+            .shard_index
+            .get(&shard_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Full vertices for a shard, in shard-index order, starting after the
+    /// optional cursor hash. Uses the shard index to avoid a full scan.
+    pub fn get_vertices_in_shard(
+        &self,
+        shard_id: u32,
+        limit: usize,
+        after: Option<VertexHash>,
+    ) -> Result<Vec<DAGVertex>, DAGError> {
+        let hashes: Vec<VertexHash> = {
+            let indices = self.indices.read().unwrap();
+            let all = indices.shard_index.get(&shard_id);
+            match all {
+                Some(all) => {
+                    let skip = match after {
+                        Some(cursor) => all
+                            .iter()
+                            .position(|h| *h == cursor)
+                            .map(|i| i + 1)
+                            .unwrap_or(0),
+                        None => 0,
+                    };
+                    all.iter().skip(skip).take(limit).copied().collect()
+                }
+                None => Vec::new(),
+            }
+        };
+        let mut vertices = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            if let Some(vertex) = self.get_vertex(&hash)? {
+                vertices.push(vertex);
+            }
+        }
+        Ok(vertices)
+    }
+
+    /// Vertices whose logical clock falls in `[start, end]`, in `(clock,
+    /// hash)` order, at most `limit` per page. `after` resumes past a cursor
+    /// from a previous page; the returned cursor is `Some` whenever the page
+    /// was filled and more vertices may follow.
+    pub fn get_vertices_by_clock_range(
+        &self,
+        start: u64,
+        end: u64,
+        limit: usize,
+        after: Option<Cursor>,
+    ) -> Result<(Vec<DAGVertex>, Option<Cursor>), DAGError> {
+        let hashes: Vec<(u64, VertexHash)> = {
+            let indices = self.indices.read().unwrap();
+            let first_clock = after.map(|c| c.clock).unwrap_or(start).max(start);
+            let mut clocks: Vec<u64> = indices
+                .clock_index
+                .keys()
+                .copied()
+                .filter(|clock| (first_clock..=end).contains(clock))
+                .collect();
+            clocks.sort_unstable();
+            let mut page = Vec::new();
+            'clocks: for clock in clocks {
+                // Buckets are hash-sorted by `index_vertex`, so same-clock
+                // vertices page out identically on every node.
+                for &hash in &indices.clock_index[&clock] {
+                    if let Some(cursor) = after {
+                        if clock == cursor.clock && hash <= cursor.hash {
+                            continue;
+                        }
+                    }
+                    page.push((clock, hash));
+                    if page.len() == limit {
+                        break 'clocks;
+                    }
+                }
+            }
+            page
+        };
+        let next = if hashes.len() == limit {
+            hashes.last().map(|(clock, hash)| Cursor {
+                clock: *clock,
+                hash: *hash,
+            })
+        } else {
+            None
+        };
+        let mut vertices = Vec::with_capacity(hashes.len());
+        for (_, hash) in hashes {
+            if let Some(vertex) = self.get_vertex(&hash)? {
+                vertices.push(vertex);
+            }
+        }
+        Ok((vertices, next))
+    }
+
+    /// Vertices whose wall-clock timestamp falls in `[start_ms, end_ms]`,
+    /// in `(timestamp, hash)` order, at most `limit`. Served from the
+    /// bucketed time index, so only the buckets overlapping the range are
+    /// touched.
+    pub fn get_vertices_by_time_range(
+        &self,
+        start_ms: u64,
+        end_ms: u64,
+        limit: usize,
+    ) -> Result<Vec<DAGVertex>, DAGError> {
+        let hashes: Vec<VertexHash> = {
+            let indices = self.indices.read().unwrap();
+            indices
+                .time_index
+                .range(start_ms / TIME_BUCKET_MS..=end_ms / TIME_BUCKET_MS)
+                .flat_map(|(_, bucket)| bucket.iter().copied())
+                .collect()
+        };
+        let mut in_range = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            if let Some(vertex) = self.get_vertex(&hash)? {
+                // Buckets are coarser than the query; drop edge vertices
+                // outside the exact range.
+                if (start_ms..=end_ms).contains(&vertex.timestamp) {
+                    in_range.push(vertex);
+                }
+            }
+        }
+        in_range.sort_by_key(|vertex| (vertex.timestamp, vertex.tx_hash));
+        in_range.truncate(limit);
+        Ok(in_range)
+    }
+
+    /// Transactions touching `address` as source or target, in `(clock,
+    /// hash)` order, paged the same way as [`get_vertices_by_clock_range`].
+    ///
+    /// [`get_vertices_by_clock_range`]: DAGVertexStore::get_vertices_by_clock_range
+    pub fn get_transactions_for_address(
+        &self,
+        address: &str,
+        limit: usize,
+        after: Option<Cursor>,
+    ) -> Result<(Vec<DAGVertex>, Option<Cursor>), DAGError> {
+        let hashes: Vec<VertexHash> = self
+            .indices
+            .read()
+            .unwrap()
+            .address_index
+            .get(address)
+            .cloned()
+            .unwrap_or_default();
+        let mut ordered = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            if let Some(vertex) = self.get_vertex(&hash)? {
+                ordered.push((vertex.logical_clock, vertex));
+            }
+        }
+        ordered.sort_by_key(|(clock, vertex)| (*clock, vertex.tx_hash));
+        let page: Vec<DAGVertex> = ordered
+            .into_iter()
+            .filter(|(clock, vertex)| match after {
+                Some(cursor) => (*clock, vertex.tx_hash) > (cursor.clock, cursor.hash),
+                None => true,
+            })
+            .map(|(_, vertex)| vertex)
+            .take(limit)
+            .collect();
+        let next = if page.len() == limit {
+            page.last().map(|vertex| Cursor {
+                clock: vertex.logical_clock,
+                hash: vertex.tx_hash,
+            })
+        } else {
+            None
+        };
+        Ok((page, next))
+    }
+
+    /// Current tips: stored vertices whose child count is 0. Served straight
+    /// from the incremental count index, no per-vertex children scan.
+    pub fn get_tips(&self) -> Vec<VertexHash> {
+        self.indices
+            .read()
+            .unwrap()
+            .child_count
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(hash, _)| *hash)
+            .collect()
+    }
+
+    /// Number of children referencing `hash` as a parent.
+    pub fn child_count(&self, hash: &VertexHash) -> u32 {
+        self.indices
+            .read()
+            .unwrap()
+            .child_count
+            .get(hash)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Rebuilds the in-memory indices from the backing store, logging
+    /// progress so a large rebuild at startup doesn't look like a hang.
+    pub fn rebuild_indices(&self) -> Result<(), DAGError> {
+        let vertices = self.all_vertices()?;
+        let total = vertices.len();
+        {
+            let mut indices = self.indices.write().unwrap();
+            *indices = DAGIndices::default();
+            for (processed, vertex) in vertices.iter().enumerate() {
+                indices.index_vertex(vertex);
+                if (processed + 1) % REBUILD_PROGRESS_INTERVAL == 0 {
+                    log::info!("index rebuild: {}/{total} vertices", processed + 1);
+                }
+            }
+        }
+        if total >= REBUILD_PROGRESS_INTERVAL {
+            log::info!("index rebuild complete: {total} vertices");
+        }
+        self.stats
+            .total_vertices
+            .store(total as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn all_vertices(&self) -> Result<Vec<DAGVertex>, DAGError> {
+        let mut all = Vec::new();
+        for backend in self.all_backends() {
+            all.extend(backend.all_vertices()?);
+        }
+        Ok(all)
+    }
+
+    pub fn vertex_count(&self) -> u64 {
+        self.all_backends().map(Backend::vertex_count).sum()
+    }
+
+    /// Snapshot of the statistics counters. Each counter is read atomically;
+    /// the snapshot as a whole is not a consistent point in time.
+    pub fn get_stats(&self) -> StorageStats {
+        StorageStats {
+            total_vertices: self.stats.total_vertices.load(Ordering::Relaxed),
+            logical_size_bytes: self.stats.logical_size_bytes.load(Ordering::Relaxed),
+            physical_size_bytes: self.stats.physical_size_bytes.load(Ordering::Relaxed),
+            cache_hits: self.stats.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.stats.cache_misses.load(Ordering::Relaxed),
+            read_operations: self.stats.read_operations.load(Ordering::Relaxed),
+            write_operations: self.stats.write_operations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Loads the `count` most recent vertices (by logical clock) into the
+    /// vertex cache so a freshly opened store doesn't serve its first
+    /// queries from disk. Stops at the cache capacity and returns how many
+    /// vertices were actually warmed.
+    pub fn warm_cache(&self, count: usize) -> usize {
+        let mut targets: Vec<VertexHash> = Vec::new();
+        {
+            let indices = self.indices.read().unwrap();
+            let mut clocks: Vec<u64> = indices.clock_index.keys().copied().collect();
+            clocks.sort_unstable_by(|a, b| b.cmp(a));
+            'clocks: for clock in clocks {
+                for hash in &indices.clock_index[&clock] {
+                    if targets.len() >= count {
+                        break 'clocks;
+                    }
+                    targets.push(*hash);
+                }
+            }
+        }
+        let mut warmed = 0;
+        for hash in targets {
+            if self.vertex_cache.read().unwrap().contains_key(&hash) {
+                continue;
+            }
+            let Ok(Some(vertex)) = self.load_vertex(&hash) else {
+                continue;
+            };
+            let mut cache = self.vertex_cache.write().unwrap();
+            if cache.len() >= self.cache_size {
+                break;
+            }
+            cache.insert(hash, vertex);
+            warmed += 1;
+        }
+        warmed
+    }
+
+    /// Forces a durable sync of pending writes, returning the bytes flushed
+    /// (0 for backends that don't report a size).
+    pub fn flush(&self) -> Result<u64, DAGError> {
+        #[cfg(test)]
+        if self.fail_writes.load(Ordering::Relaxed) {
+            return Err(DAGError::StorageError(
+                "no space left on device (injected)".into(),
+            ));
+        }
+        let mut bytes = 0;
+        for backend in self.all_backends() {
+            bytes += backend.flush()?;
+        }
+        Ok(bytes)
+    }
+
+    /// Proactive compaction; returns the post-compaction on-disk size.
+    /// Writes a point-in-time snapshot of every vertex to
+    /// `dir/vertices.bin`, flushing pending writes first. Returns the
+    /// snapshot size in bytes.
+    pub fn snapshot_to(&self, dir: &Path) -> Result<u64, DAGError> {
+        self.flush()?;
+        std::fs::create_dir_all(dir)?;
+        let vertices = self.all_vertices()?;
+        let bytes = bincode::serialize(&vertices)?;
+        std::fs::write(dir.join("vertices.bin"), &bytes)?;
+        Ok(bytes.len() as u64)
+    }
+
+    pub fn compact(&self) -> Result<u64, DAGError> {
+        let mut size = 0;
+        for backend in self.all_backends() {
+            size += backend.compact()?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::TransactionData;
+
+    fn sample_vertex(clock: u64, parents: Vec<VertexHash>) -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 10,
+            currency: 1,
+            nonce: clock,
+            fee: 1,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, parents, clock, 0)
+    }
+
+    fn roundtrip_on(backend: StorageBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, backend).unwrap();
+        let vertex = sample_vertex(0, vec![]);
+        store.store_vertex(&vertex).unwrap();
+        assert_eq!(
+            store.get_vertex(&vertex.tx_hash).unwrap().unwrap().tx_hash,
+            vertex.tx_hash
+        );
+    }
+
+    #[cfg(feature = "sled-backend")]
+    #[test]
+    fn flushed_writes_survive_a_store_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let hashes: Vec<VertexHash> = {
+            let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::Sled).unwrap();
+            let mut hashes = Vec::new();
+            for clock in 0..5 {
+                let vertex = sample_vertex(clock, vec![]);
+                store.store_vertex(&vertex).unwrap();
+                hashes.push(vertex.tx_hash);
+            }
+            store.flush().unwrap();
+            hashes
+        };
+
+        let reopened = DAGVertexStore::new(dir.path(), 16, StorageBackend::Sled).unwrap();
+        assert_eq!(reopened.vertex_count(), 5);
+        for hash in &hashes {
+            assert!(reopened.get_vertex(hash).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn time_range_queries_return_exactly_the_vertices_in_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::Memory).unwrap();
+        let mut by_timestamp = HashMap::new();
+        for (clock, timestamp) in [(0u64, 500u64), (1, 1_200), (2, 1_900), (3, 2_500), (4, 9_000)]
+        {
+            let mut vertex = sample_vertex(clock, vec![]);
+            vertex.timestamp = timestamp;
+            store.store_vertex(&vertex).unwrap();
+            by_timestamp.insert(timestamp, vertex.tx_hash);
+        }
+
+        // 1_200 shares a bucket with the range start but sits before it;
+        // the exact-range filter must drop it.
+        let hits = store.get_vertices_by_time_range(1_300, 2_600, 100).unwrap();
+        assert_eq!(
+            hits.iter().map(|v| v.tx_hash).collect::<Vec<_>>(),
+            vec![by_timestamp[&1_900], by_timestamp[&2_500]]
+        );
+
+        let limited = store.get_vertices_by_time_range(0, u64::MAX, 2).unwrap();
+        assert_eq!(limited.len(), 2);
+        assert_eq!(limited[0].tx_hash, by_timestamp[&500]);
+    }
+
+    #[test]
+    fn same_clock_vertices_come_back_hash_sorted_whatever_the_insert_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::Memory).unwrap();
+        let mut hashes = Vec::new();
+        // Distinct nonces give distinct hashes at one shared clock.
+        for nonce in [4u64, 0, 3, 1, 2] {
+            let tx = TransactionData {
+                source: "a".into(),
+                target: "b".into(),
+                amount: 10,
+                currency: 1,
+                nonce,
+                fee: 1,
+                user_data: Vec::new(),
+                outputs: Vec::new(),
+            };
+            let vertex = DAGVertex::new(tx, vec![], 7, 0);
+            store.store_vertex(&vertex).unwrap();
+            hashes.push(vertex.tx_hash);
+        }
+        hashes.sort_unstable();
+
+        let (page, _) = store.get_vertices_by_clock_range(0, 10, 100, None).unwrap();
+        let got: Vec<VertexHash> = page.iter().map(|v| v.tx_hash).collect();
+        assert_eq!(got, hashes);
+
+        // A rebuilt index orders them the same way.
+        store.rebuild_indices().unwrap();
+        let (page, _) = store.get_vertices_by_clock_range(0, 10, 100, None).unwrap();
+        let got: Vec<VertexHash> = page.iter().map(|v| v.tx_hash).collect();
+        assert_eq!(got, hashes);
+    }
+
+    #[cfg(feature = "sled-backend")]
+    #[test]
+    fn compression_shrinks_physical_size_below_logical() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new_partitioned(
+            dir.path(),
+            16,
+            StorageBackend::Sled,
+            &[],
+            true,
+        )
+        .unwrap();
+        let mut hashes = Vec::new();
+        for clock in 0..10 {
+            let mut vertex = sample_vertex(clock, vec![]);
+            // Highly compressible payload.
+            vertex.transaction_data.user_data = vec![0u8; 4_096];
+            store.store_vertex(&vertex).unwrap();
+            hashes.push(vertex.tx_hash);
+        }
+
+        let stats = store.get_stats();
+        assert!(stats.logical_size_bytes > 40_000);
+        assert!(
+            stats.physical_size_bytes < stats.logical_size_bytes,
+            "physical {} not below logical {}",
+            stats.physical_size_bytes,
+            stats.logical_size_bytes
+        );
+
+        // Compressed bodies read back intact, cold cache included.
+        drop(store);
+        let reopened = DAGVertexStore::new_partitioned(
+            dir.path(),
+            16,
+            StorageBackend::Sled,
+            &[],
+            true,
+        )
+        .unwrap();
+        for hash in &hashes {
+            let vertex = reopened.get_vertex(hash).unwrap().unwrap();
+            assert_eq!(vertex.transaction_data.user_data.len(), 4_096);
+        }
+    }
+
+    #[cfg(feature = "sled-backend")]
+    #[test]
+    fn partitioned_shards_land_in_their_own_store() {
+        let main_dir = tempfile::tempdir().unwrap();
+        let shard_dir = tempfile::tempdir().unwrap();
+        let partitions = [ShardPartition {
+            shards: 1..=1,
+            path: shard_dir.path().to_path_buf(),
+        }];
+
+        let (hash0, hash1) = {
+            let store = DAGVertexStore::new_partitioned(
+                main_dir.path(),
+                16,
+                StorageBackend::Sled,
+                &partitions,
+                false,
+            )
+            .unwrap();
+            let mut v0 = sample_vertex(0, vec![]);
+            v0.shard_id = 0;
+            let mut v1 = sample_vertex(1, vec![]);
+            v1.shard_id = 1;
+            store.store_vertex(&v0).unwrap();
+            store.store_vertex(&v1).unwrap();
+            // Both shards answer through the unified facade.
+            assert!(store.get_vertex(&v0.tx_hash).unwrap().is_some());
+            assert!(store.get_vertex(&v1.tx_hash).unwrap().is_some());
+            assert_eq!(store.vertex_count(), 2);
+            store.flush().unwrap();
+            (v0.tx_hash, v1.tx_hash)
+        };
+
+        // Each vertex is persisted only under its shard's directory.
+        let main_only = DAGVertexStore::new(main_dir.path(), 16, StorageBackend::Sled).unwrap();
+        assert!(main_only.get_vertex(&hash0).unwrap().is_some());
+        assert!(main_only.get_vertex(&hash1).unwrap().is_none());
+        drop(main_only);
+        let shard_only = DAGVertexStore::new(shard_dir.path(), 16, StorageBackend::Sled).unwrap();
+        assert!(shard_only.get_vertex(&hash1).unwrap().is_some());
+        assert!(shard_only.get_vertex(&hash0).unwrap().is_none());
+    }
+
+    #[cfg(feature = "sled-backend")]
+    #[test]
+    fn warming_turns_recent_vertex_queries_into_cache_hits() {
+        let dir = tempfile::tempdir().unwrap();
+        let recent = {
+            let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::Sled).unwrap();
+            let mut parent = sample_vertex(0, vec![]);
+            store.store_vertex(&parent).unwrap();
+            for clock in 1..=4 {
+                let vertex = sample_vertex(clock, vec![parent.tx_hash]);
+                store.store_vertex(&vertex).unwrap();
+                parent = vertex;
+            }
+            parent.tx_hash
+        };
+
+        // A reopened store starts cold; warming pulls the newest vertices
+        // back in so querying them doesn't miss to disk.
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::Sled).unwrap();
+        assert_eq!(store.warm_cache(3), 3);
+        let before = store.get_stats();
+        store.get_vertex(&recent).unwrap().unwrap();
+        let after = store.get_stats();
+        assert_eq!(after.cache_hits, before.cache_hits + 1);
+        assert_eq!(after.cache_misses, before.cache_misses);
+    }
+
+    #[cfg(feature = "sled-backend")]
+    #[test]
+    fn sled_backend_selected_at_runtime() {
+        roundtrip_on(StorageBackend::Sled);
+    }
+
+    #[cfg(feature = "rocksdb-backend")]
+    #[test]
+    fn rocksdb_backend_selected_at_runtime() {
+        roundtrip_on(StorageBackend::RocksDb);
+    }
+
+    #[test]
+    fn memory_backend_always_available() {
+        roundtrip_on(StorageBackend::Memory);
+    }
+
+    #[cfg(not(feature = "rocksdb-backend"))]
+    #[test]
+    fn uncompiled_backend_errors_clearly() {
+        let dir = tempfile::tempdir().unwrap();
+        let Err(err) = DAGVertexStore::new(dir.path(), 16, StorageBackend::RocksDb) else {
+            panic!("expected uncompiled backend to error");
+        };
+        assert!(matches!(err, DAGError::StorageError(_)));
+        assert!(err.to_string().contains("rocksdb-backend"));
+    }
+
+    #[test]
+    fn tips_track_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        let genesis = sample_vertex(0, vec![]);
+        store.store_vertex(&genesis).unwrap();
+        assert_eq!(store.get_tips(), vec![genesis.tx_hash]);
+
+        let child = sample_vertex(1, vec![genesis.tx_hash]);
+        store.store_vertex(&child).unwrap();
+        assert_eq!(store.get_tips(), vec![child.tx_hash]);
+        assert_eq!(store.child_count(&genesis.tx_hash), 1);
+        assert_eq!(store.child_count(&child.tx_hash), 0);
+    }
+
+    #[test]
+    fn rebuilding_indices_reproduces_the_incremental_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        let mut hashes = Vec::new();
+        for clock in 0..200u64 {
+            let parents = if clock < 2 {
+                vec![]
+            } else {
+                vec![hashes[clock as usize - 1], hashes[clock as usize - 2]]
+            };
+            let vertex = sample_vertex(clock, parents);
+            hashes.push(vertex.tx_hash);
+            store.store_vertex(&vertex).unwrap();
+        }
+
+        let mut tips_before = store.get_tips();
+        tips_before.sort();
+        let counts_before: Vec<u32> = hashes.iter().map(|h| store.child_count(h)).collect();
+        let children_before: Vec<_> = hashes
+            .iter()
+            .map(|h| {
+                // The backend iterates in its own order, so compare the
+                // children as sets.
+                let mut children = store.get_children(h).unwrap();
+                children.sort();
+                children
+            })
+            .collect();
+
+        store.rebuild_indices().unwrap();
+
+        let mut tips_after = store.get_tips();
+        tips_after.sort();
+        assert_eq!(tips_before, tips_after);
+        let counts_after: Vec<u32> = hashes.iter().map(|h| store.child_count(h)).collect();
+        assert_eq!(counts_before, counts_after);
+        for (hash, before) in hashes.iter().zip(children_before) {
+            let mut after = store.get_children(hash).unwrap();
+            after.sort();
+            assert_eq!(after, before);
+        }
+    }
+
+    #[test]
+    fn child_count_tips_match_a_brute_force_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        let g1 = sample_vertex(0, vec![]);
+        let g2 = sample_vertex(1, vec![]);
+        let left = sample_vertex(2, vec![g1.tx_hash, g2.tx_hash]);
+        let right = sample_vertex(3, vec![g1.tx_hash, g2.tx_hash]);
+        for vertex in [&g1, &g2, &left, &right] {
+            store.store_vertex(vertex).unwrap();
+        }
+
+        let mut tips = store.get_tips();
+        tips.sort();
+        let mut brute: Vec<VertexHash> = store
+            .all_vertices()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.tx_hash)
+            .filter(|h| store.get_children(h).unwrap().is_empty())
+            .collect();
+        brute.sort();
+        assert_eq!(tips, brute);
+        assert_eq!(store.child_count(&g1.tx_hash), 2);
+
+        // Adding a child moves the tip and bumps the parents' counts.
+        let merge = sample_vertex(4, vec![left.tx_hash, right.tx_hash]);
+        store.store_vertex(&merge).unwrap();
+        assert_eq!(store.child_count(&left.tx_hash), 1);
+        assert_eq!(store.child_count(&right.tx_hash), 1);
+        assert_eq!(store.get_tips(), vec![merge.tx_hash]);
+    }
+
+    #[test]
+    fn clock_range_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        for clock in 0..5 {
+            store.store_vertex(&sample_vertex(clock, vec![])).unwrap();
+        }
+        let (range, next) = store.get_vertices_by_clock_range(1, 3, 10, None).unwrap();
+        assert_eq!(range.len(), 3);
+        assert!(next.is_none());
+        assert!(range.iter().all(|v| (1..=3).contains(&v.logical_clock)));
+    }
+
+    #[test]
+    fn clock_range_pages_without_gaps_or_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        let mut expected = std::collections::HashSet::new();
+        for i in 0..250u64 {
+            // Five vertices per clock so pages split within a clock.
+            let mut vertex = sample_vertex(i / 5, vec![]);
+            vertex.transaction_data.nonce = i;
+            vertex.tx_hash = vertex.calculate_hash();
+            expected.insert(vertex.tx_hash);
+            store.store_vertex(&vertex).unwrap();
+        }
+        assert_eq!(expected.len(), 250);
+
+        let mut seen = std::collections::HashSet::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let (page, next) = store
+                .get_vertices_by_clock_range(0, u64::MAX, 50, cursor)
+                .unwrap();
+            for vertex in &page {
+                assert!(seen.insert(vertex.tx_hash), "duplicate across pages");
+            }
+            pages += 1;
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, expected);
+        assert!(pages >= 5);
+    }
+
+    #[test]
+    fn concurrent_reads_keep_counters_accurate() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = std::sync::Arc::new(
+            DAGVertexStore::new(dir.path(), 16, StorageBackend::Memory).unwrap(),
+        );
+        let vertex = sample_vertex(0, vec![]);
+        store.store_vertex(&vertex).unwrap();
+        let base = store.get_stats();
+
+        const THREADS: usize = 8;
+        const READS: u64 = 250;
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let store = store.clone();
+                let hash = vertex.tx_hash;
+                std::thread::spawn(move || {
+                    for _ in 0..READS {
+                        assert!(store.get_vertex(&hash).unwrap().is_some());
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let total = THREADS as u64 * READS;
+        let stats = store.get_stats();
+        assert_eq!(stats.read_operations - base.read_operations, total);
+        assert_eq!(stats.cache_hits - base.cache_hits, total);
+        assert_eq!(stats.cache_misses, base.cache_misses);
+    }
+
+    #[test]
+    fn cache_stats_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        let vertex = sample_vertex(0, vec![]);
+        store.store_vertex(&vertex).unwrap();
+        store.get_vertex(&vertex.tx_hash).unwrap();
+        let stats = store.get_stats();
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(stats.total_vertices, 1);
+    }
+
+    #[test]
+    fn address_history_returns_transfers_in_clock_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DAGVertexStore::new(dir.path(), 16, StorageBackend::default()).unwrap();
+        for clock in [3u64, 1, 2] {
+            let mut vertex = sample_vertex(clock, vec![]);
+            vertex.transaction_data.source = "alice".into();
+            vertex.tx_hash = vertex.calculate_hash();
+            store.store_vertex(&vertex).unwrap();
+        }
+        // One incoming transfer and one unrelated vertex.
+        let mut incoming = sample_vertex(4, vec![]);
+        incoming.transaction_data.target = "alice".into();
+        incoming.tx_hash = incoming.calculate_hash();
+        store.store_vertex(&incoming).unwrap();
+        store.store_vertex(&sample_vertex(5, vec![])).unwrap();
+
+        let (txs, next) = store
+            .get_transactions_for_address("alice", 100, None)
+            .unwrap();
+        assert_eq!(txs.len(), 4);
+        assert!(next.is_none());
+        assert!(txs.windows(2).all(|w| w[0].logical_clock <= w[1].logical_clock));
+
+        // The index survives a rebuild from the backing store.
+        store.rebuild_indices().unwrap();
+        let (rebuilt, _) = store
+            .get_transactions_for_address("alice", 100, None)
+            .unwrap();
+        assert_eq!(rebuilt.len(), 4);
+    }
+}
@@ -0,0 +1,371 @@
+//! Pluggable vertex validation: an ordered pipeline of independent rules.
+//!
+//! Each rule checks one property and the pipeline stops at the first
+//! failure, so the reported error always names the earliest violated rule.
+//! Deployments can append custom rules without touching the engine.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::DAGError;
+use crate::storage_unified::DAGVertexStore;
+use crate::vertex::DAGVertex;
+
+/// How far in the future a vertex timestamp may lie before it is rejected.
+const MAX_TIMESTAMP_DRIFT_MS: u64 = 2 * 60 * 60 * 1000;
+
+/// Everything a rule may consult besides the vertex itself.
+pub struct ValidationContext<'a> {
+    /// The store parents are resolved against.
+    pub storage: &'a DAGVertexStore,
+    /// Maximum serialized vertex size.
+    pub max_vertex_bytes: u64,
+    /// Maximum number of parents.
+    pub max_parents: usize,
+    /// When set, the only parentless zero-clock vertex accepted is the one
+    /// with exactly this hash; `None` keeps the permissive behaviour.
+    pub genesis_hash: Option<crate::vertex::VertexHash>,
+}
+
+/// One validation rule.
+pub trait VertexValidator: Send + Sync {
+    /// Stable rule name, used in logs and diagnostics.
+    fn name(&self) -> &'static str;
+    fn validate(&self, vertex: &DAGVertex, ctx: &ValidationContext<'_>) -> Result<(), DAGError>;
+}
+
+/// An ordered list of rules, applied first to last.
+#[derive(Default)]
+pub struct ValidationPipeline {
+    rules: Vec<Box<dyn VertexValidator>>,
+}
+
+impl ValidationPipeline {
+    /// An empty pipeline; every vertex passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rule set the engine uses by default, in checking order.
+    pub fn with_default_rules() -> Self {
+        let mut pipeline = Self::new();
+        pipeline.push(Box::new(SizeRule));
+        pipeline.push(Box::new(StructureRule));
+        pipeline.push(Box::new(ParentExistenceRule));
+        pipeline.push(Box::new(ClockOrderingRule));
+        pipeline.push(Box::new(SignatureRule));
+        pipeline.push(Box::new(TimestampRule));
+        pipeline.push(Box::new(ProofRule));
+        pipeline
+    }
+
+    /// Appends a rule after the existing ones.
+    pub fn push(&mut self, rule: Box<dyn VertexValidator>) {
+        self.rules.push(rule);
+    }
+
+    /// Rule names in application order.
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|r| r.name()).collect()
+    }
+
+    /// Runs every rule in order, returning the first failure.
+    pub fn validate(
+        &self,
+        vertex: &DAGVertex,
+        ctx: &ValidationContext<'_>,
+    ) -> Result<(), DAGError> {
+        for rule in &self.rules {
+            rule.validate(vertex, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rejects vertices whose serialized form exceeds the configured size.
+pub struct SizeRule;
+
+impl VertexValidator for SizeRule {
+    fn name(&self) -> &'static str {
+        "size"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        let size = vertex.serialized_size();
+        if size > ctx.max_vertex_bytes {
+            return Err(DAGError::InvalidVertex(format!(
+                "serialized size {size} exceeds max_vertex_bytes {}",
+                ctx.max_vertex_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Hash integrity, self/duplicate parent references and parent-count bounds.
+pub struct StructureRule;
+
+impl VertexValidator for StructureRule {
+    fn name(&self) -> &'static str {
+        "structure"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        vertex.validate_dag_properties()?;
+        // Genesis-style vertices (clock 0) are allowed fewer than two
+        // parents, but anyone can craft a zero clock: when a genesis hash is
+        // configured, only that exact vertex gets the exemption.
+        if vertex.parents.len() < 2 && vertex.logical_clock == 0 {
+            if let Some(expected) = ctx.genesis_hash {
+                if vertex.tx_hash != expected {
+                    return Err(DAGError::InvalidVertex(format!(
+                        "parentless vertex {} is not the configured genesis",
+                        hex::encode(&vertex.tx_hash[..8])
+                    )));
+                }
+            }
+        }
+        if vertex.parents.len() < 2 && vertex.logical_clock > 0 {
+            return Err(DAGError::InvalidVertex(format!(
+                "vertex has {} parents, minimum is 2",
+                vertex.parents.len()
+            )));
+        }
+        if vertex.parents.len() > ctx.max_parents {
+            return Err(DAGError::InvalidVertex(format!(
+                "vertex has {} parents, maximum is {}",
+                vertex.parents.len(),
+                ctx.max_parents
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Every referenced parent must already be stored.
+pub struct ParentExistenceRule;
+
+impl VertexValidator for ParentExistenceRule {
+    fn name(&self) -> &'static str {
+        "parent-existence"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        for parent in &vertex.parents {
+            if !ctx.storage.contains(parent)? {
+                return Err(DAGError::ValidationError(format!(
+                    "unknown parent {}",
+                    hex::encode(parent)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The logical clock must exceed every parent's clock.
+pub struct ClockOrderingRule;
+
+impl VertexValidator for ClockOrderingRule {
+    fn name(&self) -> &'static str {
+        "clock-ordering"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        let mut max_parent_clock = 0u64;
+        for parent in &vertex.parents {
+            if let Some(parent_vertex) = ctx.storage.get_vertex(parent)? {
+                max_parent_clock = max_parent_clock.max(parent_vertex.logical_clock);
+            }
+        }
+        if !vertex.parents.is_empty() && vertex.logical_clock <= max_parent_clock {
+            return Err(DAGError::ValidationError(format!(
+                "logical clock {} not greater than max parent clock {}",
+                vertex.logical_clock, max_parent_clock
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A present signature must at least be the right shape (64 bytes ed25519).
+/// Cryptographic verification needs the signer's key and happens where that
+/// key is known.
+pub struct SignatureRule;
+
+impl VertexValidator for SignatureRule {
+    fn name(&self) -> &'static str {
+        "signature"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, _ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        if !vertex.signature.is_empty() && vertex.signature.len() != 64 {
+            return Err(DAGError::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+/// Timestamps may not lie unreasonably far in the future.
+pub struct TimestampRule;
+
+impl VertexValidator for TimestampRule {
+    fn name(&self) -> &'static str {
+        "timestamp"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, _ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        if vertex.timestamp > now + MAX_TIMESTAMP_DRIFT_MS {
+            return Err(DAGError::ValidationError(format!(
+                "timestamp {} is too far in the future",
+                vertex.timestamp
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// An attached proof must carry actual proof bytes.
+pub struct ProofRule;
+
+impl VertexValidator for ProofRule {
+    fn name(&self) -> &'static str {
+        "proof"
+    }
+
+    fn validate(&self, vertex: &DAGVertex, _ctx: &ValidationContext<'_>) -> Result<(), DAGError> {
+        if let Some(proof) = &vertex.proof {
+            if proof.proof_data.is_empty() {
+                return Err(DAGError::ValidationError(
+                    "attached proof has no proof data".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_unified::StorageBackend;
+    use crate::vertex::TransactionData;
+    use std::sync::Mutex;
+
+    /// Records its invocation in a shared log, optionally failing.
+    struct RecordingRule {
+        name: &'static str,
+        fail: bool,
+        log: std::sync::Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl VertexValidator for RecordingRule {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn validate(
+            &self,
+            _vertex: &DAGVertex,
+            _ctx: &ValidationContext<'_>,
+        ) -> Result<(), DAGError> {
+            self.log.lock().unwrap().push(self.name);
+            if self.fail {
+                return Err(DAGError::ValidationError(format!("{} failed", self.name)));
+            }
+            Ok(())
+        }
+    }
+
+    fn sample_vertex() -> DAGVertex {
+        let tx = TransactionData {
+            source: "a".into(),
+            target: "b".into(),
+            amount: 1,
+            currency: 1,
+            nonce: 0,
+            fee: 0,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        };
+        DAGVertex::new(tx, Vec::new(), 0, 0)
+    }
+
+    #[test]
+    fn rules_run_in_order_and_short_circuit() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DAGVertexStore::new(dir.path(), 16, StorageBackend::Memory).unwrap();
+        let ctx = ValidationContext {
+            storage: &storage,
+            max_vertex_bytes: 1_048_576,
+            max_parents: 16,
+            genesis_hash: None,
+        };
+        let log = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let mut pipeline = ValidationPipeline::new();
+        pipeline.push(Box::new(RecordingRule {
+            name: "first",
+            fail: false,
+            log: log.clone(),
+        }));
+        pipeline.push(Box::new(RecordingRule {
+            name: "second",
+            fail: true,
+            log: log.clone(),
+        }));
+        pipeline.push(Box::new(RecordingRule {
+            name: "third",
+            fail: false,
+            log: log.clone(),
+        }));
+
+        assert_eq!(pipeline.rule_names(), vec!["first", "second", "third"]);
+        let result = pipeline.validate(&sample_vertex(), &ctx);
+        assert!(matches!(result, Err(DAGError::ValidationError(msg)) if msg == "second failed"));
+        // The failing rule stopped the pipeline before "third".
+        assert_eq!(*log.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn default_rules_accept_a_well_formed_vertex() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DAGVertexStore::new(dir.path(), 16, StorageBackend::Memory).unwrap();
+        let ctx = ValidationContext {
+            storage: &storage,
+            max_vertex_bytes: 1_048_576,
+            max_parents: 16,
+            genesis_hash: None,
+        };
+        let pipeline = ValidationPipeline::with_default_rules();
+        assert!(pipeline.validate(&sample_vertex(), &ctx).is_ok());
+    }
+
+    #[test]
+    fn configured_genesis_hash_rejects_forged_genesis_vertices() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DAGVertexStore::new(dir.path(), 16, StorageBackend::Memory).unwrap();
+        let genesis = sample_vertex();
+        let ctx = ValidationContext {
+            storage: &storage,
+            max_vertex_bytes: 1_048_576,
+            max_parents: 16,
+            genesis_hash: Some(genesis.tx_hash),
+        };
+        let pipeline = ValidationPipeline::with_default_rules();
+
+        // The real genesis passes.
+        assert!(pipeline.validate(&genesis, &ctx).is_ok());
+
+        // A different parentless zero-clock vertex is a forgery.
+        let mut tx = genesis.transaction_data.clone();
+        tx.nonce += 1;
+        let forged = DAGVertex::new(tx, Vec::new(), 0, 0);
+        let result = pipeline.validate(&forged, &ctx);
+        assert!(
+            matches!(result, Err(DAGError::InvalidVertex(msg)) if msg.contains("configured genesis"))
+        );
+    }
+}
@@ -0,0 +1,657 @@
+//! Core DAG vertex and transaction types.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::DAGError;
+
+/// 32-byte vertex identifier (SHA-256 of the vertex contents).
+pub type VertexHash = [u8; 32];
+
+/// Number of leading zero bits in a hash, as used by mining difficulty.
+pub fn leading_zero_bits(hash: &VertexHash) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+    bits
+}
+
+/// Returns the current wall clock in milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// One additional output of a multi-transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferOutput {
+    pub target: String,
+    pub amount: u64,
+    pub currency: u32,
+}
+
+/// Transaction payload carried by a vertex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionData {
+    /// Source address (hex-encoded public key hash). `"coinbase"` for rewards.
+    pub source: String,
+    /// Target address.
+    pub target: String,
+    /// Amount in the smallest currency unit.
+    pub amount: u64,
+    /// Currency identifier. `1` is the native CS currency.
+    pub currency: u32,
+    /// Per-source sequence number.
+    pub nonce: u64,
+    /// Fee paid in CS, in the smallest unit.
+    pub fee: u64,
+    /// Opaque user payload (JSON for CNS / ordinal operations).
+    pub user_data: Vec<u8>,
+    /// Extra outputs beyond `target`/`amount`; the debit covers them all
+    /// atomically. Empty for plain single transfers.
+    pub outputs: Vec<TransferOutput>,
+}
+
+/// A CNS (name service) operation carried in `user_data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CnsOp {
+    /// Operation verb, e.g. `"reg"`.
+    pub op: String,
+    /// Name the operation targets, e.g. `"alice.cs"`.
+    #[serde(default)]
+    pub name: String,
+}
+
+/// An ordinal inscription carried in `user_data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrdinalOp {
+    /// Operation verb, e.g. `"inscribe"`.
+    pub op: String,
+    /// Inscribed content.
+    #[serde(default)]
+    pub content: String,
+}
+
+/// Coarse payload classification; see [`Payload`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadType {
+    Transfer,
+    Cns,
+    Ordinal,
+    Raw,
+}
+
+/// Structured interpretation of a transaction's `user_data`.
+///
+/// On the wire a structured payload is a JSON object tagged with a `"p"`
+/// protocol field (`"cns"`, `"ord"`); anything else — including free text
+/// that merely mentions those tags — parses as [`Payload::Raw`], so
+/// pre-existing opaque payloads stay valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    /// Plain value transfer: empty `user_data`.
+    Transfer,
+    Cns(CnsOp),
+    Ordinal(OrdinalOp),
+    /// Unrecognized bytes, preserved verbatim.
+    Raw(Vec<u8>),
+}
+
+impl Payload {
+    /// Parses raw `user_data` bytes, falling back to [`Payload::Raw`] for
+    /// anything that is not a well-formed tagged operation.
+    pub fn parse(user_data: &[u8]) -> Payload {
+        if user_data.is_empty() {
+            return Payload::Transfer;
+        }
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(user_data) else {
+            return Payload::Raw(user_data.to_vec());
+        };
+        match value.get("p").and_then(|p| p.as_str()) {
+            Some("cns") => match serde_json::from_value(value) {
+                Ok(op) => Payload::Cns(op),
+                Err(_) => Payload::Raw(user_data.to_vec()),
+            },
+            Some("ord") => match serde_json::from_value(value) {
+                Ok(op) => Payload::Ordinal(op),
+                Err(_) => Payload::Raw(user_data.to_vec()),
+            },
+            _ => Payload::Raw(user_data.to_vec()),
+        }
+    }
+
+    /// Serializes the payload back into `user_data` bytes.
+    pub fn to_user_data(&self) -> Vec<u8> {
+        match self {
+            Payload::Transfer => Vec::new(),
+            Payload::Cns(op) => {
+                let mut value = serde_json::to_value(op).unwrap();
+                value["p"] = serde_json::Value::from("cns");
+                value.to_string().into_bytes()
+            }
+            Payload::Ordinal(op) => {
+                let mut value = serde_json::to_value(op).unwrap();
+                value["p"] = serde_json::Value::from("ord");
+                value.to_string().into_bytes()
+            }
+            Payload::Raw(bytes) => bytes.clone(),
+        }
+    }
+
+    pub fn payload_type(&self) -> PayloadType {
+        match self {
+            Payload::Transfer => PayloadType::Transfer,
+            Payload::Cns(_) => PayloadType::Cns,
+            Payload::Ordinal(_) => PayloadType::Ordinal,
+            Payload::Raw(_) => PayloadType::Raw,
+        }
+    }
+}
+
+impl TransactionData {
+    /// Parses `user_data` into its structured [`Payload`].
+    pub fn payload(&self) -> Payload {
+        Payload::parse(&self.user_data)
+    }
+
+    /// The payload's protocol classification.
+    pub fn payload_type(&self) -> PayloadType {
+        self.payload().payload_type()
+    }
+}
+
+/// Hash scheme that predates proof coverage: the `proof` field is not part
+/// of the hash.
+pub const HASH_SCHEME_LEGACY: u8 = 1;
+
+/// Current hash scheme: commits to the scheme byte and the proof as well.
+pub const HASH_SCHEME_WITH_PROOF: u8 = 2;
+
+/// Optional zero-knowledge proof attached to a vertex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZKProof {
+    pub proof_data: Vec<u8>,
+    pub public_inputs: Vec<u8>,
+}
+
+/// Current vertex serialization format version; see
+/// [`DAGVertex::to_versioned_bytes`].
+pub const VERTEX_FORMAT_VERSION: u8 = 2;
+
+/// Version byte leading [`DAGVertex::canonical_signing_bytes`].
+pub const SIGNING_LAYOUT_VERSION: u8 = 1;
+
+/// The v1 transaction shape, before multi-transfer `outputs` existed.
+#[derive(Serialize, Deserialize)]
+struct TransactionDataV1 {
+    source: String,
+    target: String,
+    amount: u64,
+    currency: u32,
+    nonce: u64,
+    fee: u64,
+    user_data: Vec<u8>,
+}
+
+impl From<TransactionDataV1> for TransactionData {
+    fn from(v1: TransactionDataV1) -> Self {
+        TransactionData {
+            source: v1.source,
+            target: v1.target,
+            amount: v1.amount,
+            currency: v1.currency,
+            nonce: v1.nonce,
+            fee: v1.fee,
+            user_data: v1.user_data,
+            outputs: Vec::new(),
+        }
+    }
+}
+
+/// The v1 vertex shape, before `hash_scheme` existed. Kept so old records
+/// deserialize and upgrade transparently.
+#[derive(Serialize, Deserialize)]
+struct DAGVertexV1 {
+    tx_hash: VertexHash,
+    logical_clock: u64,
+    shard_id: u32,
+    timestamp: u64,
+    parents: Vec<VertexHash>,
+    transaction_data: TransactionDataV1,
+    signature: Vec<u8>,
+    proof: Option<ZKProof>,
+}
+
+impl From<DAGVertexV1> for DAGVertex {
+    fn from(v1: DAGVertexV1) -> Self {
+        DAGVertex {
+            tx_hash: v1.tx_hash,
+            logical_clock: v1.logical_clock,
+            shard_id: v1.shard_id,
+            timestamp: v1.timestamp,
+            parents: v1.parents,
+            transaction_data: v1.transaction_data.into(),
+            signature: v1.signature,
+            proof: v1.proof,
+            hash_scheme: HASH_SCHEME_LEGACY,
+        }
+    }
+}
+
+/// A vertex in the transaction DAG.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DAGVertex {
+    /// Hash of this vertex, computed by [`DAGVertex::calculate_hash`].
+    pub tx_hash: VertexHash,
+    /// Lamport clock: `max(parent clocks) + 1`, 0 only for genesis.
+    pub logical_clock: u64,
+    /// Shard this vertex is assigned to.
+    pub shard_id: u32,
+    /// Wall-clock creation time in milliseconds.
+    pub timestamp: u64,
+    /// Hashes of approved parent vertices.
+    pub parents: Vec<VertexHash>,
+    /// The transaction carried by this vertex.
+    pub transaction_data: TransactionData,
+    /// Ed25519 signature by the source over the vertex contents.
+    pub signature: Vec<u8>,
+    /// Optional ZK proof.
+    pub proof: Option<ZKProof>,
+    /// Hashing scheme `tx_hash` was computed with; see [`DAGVertex::calculate_hash`].
+    pub hash_scheme: u8,
+}
+
+impl DAGVertex {
+    /// Creates a new unsigned vertex and computes its hash.
+    pub fn new(
+        transaction_data: TransactionData,
+        parents: Vec<VertexHash>,
+        logical_clock: u64,
+        shard_id: u32,
+    ) -> Self {
+        let mut vertex = DAGVertex {
+            tx_hash: [0u8; 32],
+            logical_clock,
+            shard_id,
+            timestamp: now_millis(),
+            parents,
+            transaction_data,
+            signature: Vec::new(),
+            proof: None,
+            hash_scheme: HASH_SCHEME_WITH_PROOF,
+        };
+        vertex.tx_hash = vertex.calculate_hash();
+        vertex
+    }
+
+    /// Computes the SHA-256 hash over the vertex fields.
+    ///
+    /// Scheme [`HASH_SCHEME_WITH_PROOF`] hashes, in order: the scheme byte,
+    /// logical_clock (LE), shard_id (LE), timestamp (LE), each parent hash in
+    /// order, the bincode serialization of transaction_data, the signature
+    /// bytes, then a proof presence byte (`0`/`1`) followed — when present —
+    /// by proof_data and public_inputs. All integers are little-endian.
+    ///
+    /// Scheme [`HASH_SCHEME_LEGACY`] omits the scheme byte and everything
+    /// after the signature, so vertices hashed before proof coverage keep
+    /// their hashes.
+    pub fn calculate_hash(&self) -> VertexHash {
+        let mut hasher = Sha256::new();
+        if self.hash_scheme >= HASH_SCHEME_WITH_PROOF {
+            hasher.update([self.hash_scheme]);
+        }
+        hasher.update(self.logical_clock.to_le_bytes());
+        hasher.update(self.shard_id.to_le_bytes());
+        hasher.update(self.timestamp.to_le_bytes());
+        for parent in &self.parents {
+            hasher.update(parent);
+        }
+        if let Ok(tx_bytes) = bincode::serialize(&self.transaction_data) {
+            hasher.update(&tx_bytes);
+        }
+        hasher.update(&self.signature);
+        if self.hash_scheme >= HASH_SCHEME_WITH_PROOF {
+            match &self.proof {
+                Some(proof) => {
+                    hasher.update([1u8]);
+                    hasher.update(&proof.proof_data);
+                    hasher.update(&proof.public_inputs);
+                }
+                None => hasher.update([0u8]),
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /// Signs the vertex with the given key and refreshes the hash.
+    pub fn sign(&mut self, key: &SigningKey) {
+        let payload = self.canonical_signing_bytes();
+        let sig: Signature = key.sign(&payload);
+        self.signature = sig.to_bytes().to_vec();
+        self.tx_hash = self.calculate_hash();
+    }
+
+    /// Verifies the vertex signature against the given public key.
+    pub fn verify_signature(&self, key: &VerifyingKey) -> Result<(), DAGError> {
+        let sig_bytes: [u8; 64] = self
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| DAGError::InvalidSignature)?;
+        let sig = Signature::from_bytes(&sig_bytes);
+        key.verify(&self.canonical_signing_bytes(), &sig)
+            .map_err(|_| DAGError::InvalidSignature)
+    }
+
+    /// The canonical bytes covered by the signature, in the explicit layout
+    /// below rather than a serializer's implicit encoding, so other
+    /// implementations (the C++ node) can reproduce them exactly.
+    ///
+    /// Layout, all integers little-endian; variable-length fields carry a
+    /// `u32` length prefix:
+    ///
+    /// ```text
+    /// u8   layout version (SIGNING_LAYOUT_VERSION)
+    /// u8   hash_scheme
+    /// u64  logical_clock
+    /// u32  shard_id
+    /// u64  timestamp
+    /// u32  parent count, then each 32-byte parent hash in order
+    /// u32  source length, source bytes
+    /// u32  target length, target bytes
+    /// u64  amount
+    /// u32  currency
+    /// u64  nonce
+    /// u64  fee
+    /// u32  user_data length, user_data bytes
+    /// u32  output count, then per output:
+    ///      u32 target length, target bytes, u64 amount, u32 currency
+    /// u8   proof presence (0/1), then when present:
+    ///      u32 proof_data length, proof_data bytes,
+    ///      u32 public_inputs length, public_inputs bytes
+    /// ```
+    ///
+    /// `tx_hash` and `signature` are not covered.
+    pub fn canonical_signing_bytes(&self) -> Vec<u8> {
+        fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+            out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            out.extend_from_slice(bytes);
+        }
+
+        let tx = &self.transaction_data;
+        let mut out = Vec::with_capacity(128 + 32 * self.parents.len());
+        out.push(SIGNING_LAYOUT_VERSION);
+        out.push(self.hash_scheme);
+        out.extend_from_slice(&self.logical_clock.to_le_bytes());
+        out.extend_from_slice(&self.shard_id.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.extend_from_slice(&(self.parents.len() as u32).to_le_bytes());
+        for parent in &self.parents {
+            out.extend_from_slice(parent);
+        }
+        put_bytes(&mut out, tx.source.as_bytes());
+        put_bytes(&mut out, tx.target.as_bytes());
+        out.extend_from_slice(&tx.amount.to_le_bytes());
+        out.extend_from_slice(&tx.currency.to_le_bytes());
+        out.extend_from_slice(&tx.nonce.to_le_bytes());
+        out.extend_from_slice(&tx.fee.to_le_bytes());
+        put_bytes(&mut out, &tx.user_data);
+        out.extend_from_slice(&(tx.outputs.len() as u32).to_le_bytes());
+        for output in &tx.outputs {
+            put_bytes(&mut out, output.target.as_bytes());
+            out.extend_from_slice(&output.amount.to_le_bytes());
+            out.extend_from_slice(&output.currency.to_le_bytes());
+        }
+        match &self.proof {
+            Some(proof) => {
+                out.push(1);
+                put_bytes(&mut out, &proof.proof_data);
+                put_bytes(&mut out, &proof.public_inputs);
+            }
+            None => out.push(0),
+        }
+        out
+    }
+
+    /// Serialized size of the vertex in bytes (uncompressed bincode).
+    pub fn serialized_size(&self) -> u64 {
+        bincode::serialized_size(self).unwrap_or(0)
+    }
+
+    /// Structural DAG checks that do not need storage access: hash integrity
+    /// and duplicate parent references.
+    pub fn validate_dag_properties(&self) -> Result<(), DAGError> {
+        let mut seen = std::collections::HashSet::new();
+        for parent in &self.parents {
+            if *parent == self.tx_hash {
+                return Err(DAGError::InvalidVertex(
+                    "vertex lists itself as a parent".into(),
+                ));
+            }
+            if !seen.insert(parent) {
+                return Err(DAGError::InvalidVertex(format!(
+                    "duplicate parent reference {}",
+                    hex::encode(parent)
+                )));
+            }
+        }
+        if self.tx_hash != self.calculate_hash() {
+            return Err(DAGError::InvalidVertex(
+                "stored hash does not match computed hash".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Short hex prefix of the vertex hash, for logs.
+    pub fn short_hash(&self) -> String {
+        hex::encode(&self.tx_hash[..8])
+    }
+
+    /// Serializes the vertex as a one-byte format version followed by the
+    /// bincode payload. Storage always writes the current version.
+    pub fn to_versioned_bytes(&self) -> Result<Vec<u8>, DAGError> {
+        let mut bytes = vec![VERTEX_FORMAT_VERSION];
+        bytes.extend(bincode::serialize(self)?);
+        Ok(bytes)
+    }
+
+    /// Deserializes any supported format version, upgrading old records to
+    /// the current struct.
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<DAGVertex, DAGError> {
+        let Some((version, payload)) = bytes.split_first() else {
+            return Err(DAGError::SerializationError("empty vertex record".into()));
+        };
+        match *version {
+            1 => Ok(bincode::deserialize::<DAGVertexV1>(payload)?.into()),
+            VERTEX_FORMAT_VERSION => Ok(bincode::deserialize(payload)?),
+            other => Err(DAGError::SerializationError(format!(
+                "unsupported vertex format version {other}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn sample_tx() -> TransactionData {
+        TransactionData {
+            source: "alice".into(),
+            target: "bob".into(),
+            amount: 1_000_000,
+            currency: 1,
+            nonce: 1,
+            fee: 1000,
+            user_data: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn canonical_signing_bytes_match_the_golden_vector() {
+        let mut tx = sample_tx();
+        tx.user_data = b"hi".to_vec();
+        tx.outputs.push(TransferOutput {
+            target: "carol".into(),
+            amount: 5,
+            currency: 2,
+        });
+        let mut vertex = DAGVertex::new(tx, vec![[1u8; 32], [2u8; 32]], 5, 0);
+        vertex.timestamp = 123;
+
+        let bytes = vertex.canonical_signing_bytes();
+        // Byte-for-byte layout other implementations must reproduce; any
+        // change here is a cross-implementation signature break.
+        assert_eq!(
+            hex::encode(&bytes),
+            "01020500000000000000000000007b00000000000000020000000101010101010101010101010101010101010101010101010101010101010101020202020202020202020202020202020202020202020202020202020202020205000000616c69636503000000626f6240420f0000000000010000000100000000000000e80300000000000002000000686901000000050000006361726f6c05000000000000000200000000"
+        );
+        // Deterministic across invocations.
+        assert_eq!(bytes, vertex.canonical_signing_bytes());
+
+        // The proof is covered: attaching one extends and changes the bytes.
+        let mut with_proof = vertex.clone();
+        with_proof.proof = Some(ZKProof {
+            proof_data: vec![9, 9],
+            public_inputs: vec![7],
+        });
+        let proof_bytes = with_proof.canonical_signing_bytes();
+        assert_ne!(bytes, proof_bytes);
+        assert_eq!(proof_bytes.len(), bytes.len() + 4 + 2 + 4 + 1);
+
+        // And signatures made over the canonical bytes still verify.
+        let key = SigningKey::generate(&mut OsRng);
+        let mut signed = vertex.clone();
+        signed.sign(&key);
+        signed.verify_signature(&key.verifying_key()).unwrap();
+    }
+
+    #[test]
+    fn payloads_round_trip_and_free_text_stays_raw() {
+        let cns = Payload::Cns(CnsOp {
+            op: "reg".into(),
+            name: "alice.cs".into(),
+        });
+        assert_eq!(Payload::parse(&cns.to_user_data()), cns);
+        assert_eq!(cns.payload_type(), PayloadType::Cns);
+
+        let ord = Payload::Ordinal(OrdinalOp {
+            op: "inscribe".into(),
+            content: "hello".into(),
+        });
+        assert_eq!(Payload::parse(&ord.to_user_data()), ord);
+
+        // The legacy hand-written JSON shape still parses as CNS.
+        let legacy = br#"{"p":"cns","op":"reg","name":"alice.cs"}"#;
+        assert_eq!(Payload::parse(legacy), cns);
+
+        assert_eq!(Payload::parse(b"").payload_type(), PayloadType::Transfer);
+
+        // Free text or untagged JSON merely mentioning the protocol tag is
+        // opaque data, not a protocol operation.
+        for raw in [
+            br#"note: set "p":"cns" to register a name"#.as_slice(),
+            br#"{"memo":"docs mention \"p\":\"ord\" here"}"#.as_slice(),
+            br#"{"p":"unknown","op":"reg"}"#.as_slice(),
+        ] {
+            let parsed = Payload::parse(raw);
+            assert_eq!(parsed.payload_type(), PayloadType::Raw);
+            assert_eq!(parsed.to_user_data(), raw);
+        }
+    }
+
+    #[test]
+    fn hash_changes_with_contents() {
+        let v1 = DAGVertex::new(sample_tx(), vec![[1u8; 32], [2u8; 32]], 5, 0);
+        let mut tx = sample_tx();
+        tx.amount = 2_000_000;
+        let v2 = DAGVertex::new(tx, vec![[1u8; 32], [2u8; 32]], 5, 0);
+        assert_ne!(v1.tx_hash, v2.tx_hash);
+    }
+
+    #[test]
+    fn proof_is_covered_by_the_current_hash_scheme() {
+        let base = DAGVertex::new(sample_tx(), vec![[1u8; 32], [2u8; 32]], 5, 0);
+        let mut with_proof = base.clone();
+        with_proof.proof = Some(ZKProof {
+            proof_data: vec![1, 2, 3],
+            public_inputs: vec![4, 5],
+        });
+        with_proof.tx_hash = with_proof.calculate_hash();
+        assert_ne!(base.tx_hash, with_proof.tx_hash);
+
+        // The legacy scheme keeps pre-proof hashes stable.
+        let mut legacy = base.clone();
+        legacy.hash_scheme = HASH_SCHEME_LEGACY;
+        let mut legacy_with_proof = with_proof.clone();
+        legacy_with_proof.hash_scheme = HASH_SCHEME_LEGACY;
+        assert_eq!(legacy.calculate_hash(), legacy_with_proof.calculate_hash());
+    }
+
+    #[test]
+    fn sign_and_verify_roundtrip() {
+        let key = SigningKey::generate(&mut OsRng);
+        let mut vertex = DAGVertex::new(sample_tx(), vec![[1u8; 32], [2u8; 32]], 3, 0);
+        vertex.sign(&key);
+        assert!(vertex.verify_signature(&key.verifying_key()).is_ok());
+        vertex.transaction_data.amount += 1;
+        assert!(vertex.verify_signature(&key.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn v1_records_deserialize_and_upgrade() {
+        let current = DAGVertex::new(sample_tx(), vec![[1u8; 32], [2u8; 32]], 5, 0);
+        let v1 = DAGVertexV1 {
+            tx_hash: current.tx_hash,
+            logical_clock: current.logical_clock,
+            shard_id: current.shard_id,
+            timestamp: current.timestamp,
+            parents: current.parents.clone(),
+            transaction_data: TransactionDataV1 {
+                source: current.transaction_data.source.clone(),
+                target: current.transaction_data.target.clone(),
+                amount: current.transaction_data.amount,
+                currency: current.transaction_data.currency,
+                nonce: current.transaction_data.nonce,
+                fee: current.transaction_data.fee,
+                user_data: current.transaction_data.user_data.clone(),
+            },
+            signature: current.signature.clone(),
+            proof: None,
+        };
+        let mut blob = vec![1u8];
+        blob.extend(bincode::serialize(&v1).unwrap());
+
+        let upgraded = DAGVertex::from_versioned_bytes(&blob).unwrap();
+        assert_eq!(upgraded.tx_hash, current.tx_hash);
+        assert_eq!(upgraded.hash_scheme, HASH_SCHEME_LEGACY);
+
+        // The current format round-trips too, and unknown versions error.
+        let bytes = current.to_versioned_bytes().unwrap();
+        assert_eq!(bytes[0], VERTEX_FORMAT_VERSION);
+        let back = DAGVertex::from_versioned_bytes(&bytes).unwrap();
+        assert_eq!(back.tx_hash, current.tx_hash);
+        assert!(DAGVertex::from_versioned_bytes(&[99, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn duplicate_parents_rejected() {
+        let vertex = DAGVertex::new(sample_tx(), vec![[7u8; 32], [7u8; 32]], 2, 0);
+        assert!(matches!(
+            vertex.validate_dag_properties(),
+            Err(DAGError::InvalidVertex(_))
+        ));
+    }
+}
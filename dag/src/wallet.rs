@@ -0,0 +1,116 @@
+//! Minimal Ed25519 wallet: key generation, address derivation and signing.
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use crate::error::DAGError;
+
+/// A local wallet holding one Ed25519 keypair.
+pub struct Wallet {
+    signing_key: SigningKey,
+    address: String,
+}
+
+impl Wallet {
+    /// Generates a fresh random wallet.
+    pub fn create_new_wallet() -> Self {
+        Self::create_with_rng(&mut OsRng)
+    }
+
+    /// Generates a wallet from the supplied RNG; with a seeded RNG the
+    /// resulting keypair is reproducible.
+    pub fn create_with_rng<R: rand::RngCore + rand::CryptoRng>(rng: &mut R) -> Self {
+        let signing_key = SigningKey::generate(rng);
+        let address = Self::derive_address(&signing_key.verifying_key());
+        Wallet {
+            signing_key,
+            address,
+        }
+    }
+
+    /// Restores a wallet from raw secret key bytes.
+    pub fn from_secret_bytes(bytes: &[u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(bytes);
+        let address = Self::derive_address(&signing_key.verifying_key());
+        Wallet {
+            signing_key,
+            address,
+        }
+    }
+
+    /// Address: hex-encoded SHA-256 of the public key, truncated to 20 bytes.
+    fn derive_address(key: &VerifyingKey) -> String {
+        let digest = Sha256::digest(key.as_bytes());
+        hex::encode(&digest[..20])
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    /// Persists the secret key to a file readable only by the owner.
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), DAGError> {
+        crate::identity::write_owner_only(path, &hex::encode(self.secret_bytes()))?;
+        Ok(())
+    }
+
+    /// Loads a wallet previously written by [`Wallet::save_to_file`].
+    pub fn load_from_file(path: &std::path::Path) -> Result<Self, DAGError> {
+        let hex_str = std::fs::read_to_string(path)?;
+        let bytes = hex::decode(hex_str.trim())
+            .map_err(|e| DAGError::SerializationError(format!("bad wallet file: {e}")))?;
+        let secret: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| DAGError::SerializationError("wallet key must be 32 bytes".into()))?;
+        Ok(Self::from_secret_bytes(&secret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_is_stable_for_key() {
+        let wallet = Wallet::create_new_wallet();
+        let restored = Wallet::from_secret_bytes(&wallet.secret_bytes());
+        assert_eq!(wallet.address(), restored.address());
+        assert_eq!(wallet.address().len(), 40);
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.key");
+        let wallet = Wallet::create_new_wallet();
+        wallet.save_to_file(&path).unwrap();
+        let loaded = Wallet::load_from_file(&path).unwrap();
+        assert_eq!(wallet.address(), loaded.address());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saved_key_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.key");
+        Wallet::create_new_wallet().save_to_file(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}